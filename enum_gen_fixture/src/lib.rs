@@ -0,0 +1,50 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+//! Enums for the enum_gen tests, which match on enums defined in another crate.
+
+// The same enums in different modules need differently named callback macros
+pub mod client {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x1)]
+        Login { user: u32 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+}
+
+pub mod server {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x1)]
+        Login { user: u32 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+}
+
+pub mod event {
+    use enum_gen::*;
+
+    #[enum_gen(module = event, id_type = u8, derive(Debug, Default))]
+    pub enum Event {
+        #[attr(ID = 1)]
+        Start { delay: u32 },
+        #[attr(ID = 2)]
+        Stop,
+        // Enabled by default. The cfgs are resolved in this crate, not in the
+        // one matching on the enum
+        #[cfg(feature = "legacy")]
+        #[attr(ID = 3)]
+        Pause,
+        #[cfg(not(feature = "legacy"))]
+        #[attr(ID = 3)]
+        Suspend,
+    }
+}
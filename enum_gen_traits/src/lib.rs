@@ -0,0 +1,67 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+//! Traits implemented by `#[enum_gen(traits)]`, so the code can be generic over
+//! any enum generated with [enum_gen](https://crates.io/crates/enum_gen), and
+//! over its structs.
+//!
+//! ```rust
+//! use enum_gen_traits::{EnumGen, EnumGenVariant};
+//!
+//! fn describe<E: EnumGen>(e: &E) -> String
+//! where
+//!     E::Id: std::fmt::Debug,
+//! {
+//!     format!("{} ({:?})", e.variant_name(), e.id())
+//! }
+//!
+//! fn wrap<V: EnumGenVariant>(v: V) -> V::Enum {
+//!     <V::Enum as EnumGen>::from_variant(v)
+//! }
+//! ```
+
+#![no_std]
+
+use core::ops::RangeInclusive;
+
+/// Struct generated from an enum variant with an ID.
+pub trait EnumGenVariant: Sized {
+    /// Type of the IDs
+    type Id: 'static;
+    /// The enum this struct is a variant of
+    type Enum: EnumGen<Id = Self::Id>;
+
+    /// The primary ID - the first one specified
+    const ID: Self::Id;
+    /// All IDs matching this struct
+    const IDS: &'static [RangeInclusive<Self::Id>];
+    /// Name of the enum variant
+    const NAME: &'static str;
+
+    /// Wrap the struct in its enum variant.
+    fn into_enum(self) -> Self::Enum;
+}
+
+/// Enum generated by `#[enum_gen]`.
+pub trait EnumGen: Sized {
+    /// Type of the IDs
+    type Id: 'static;
+
+    /// The primary IDs of all variants, except the default one
+    const PRIMARY_IDS: &'static [Self::Id];
+    /// The names of all variants
+    const VARIANT_NAMES: &'static [&'static str];
+
+    /// The primary ID of the variant, or the ID captured by the default variant.
+    /// `None` for the default variant without a captured ID.
+    fn id(&self) -> Option<Self::Id>;
+
+    /// The name of the variant.
+    fn variant_name(&self) -> &'static str;
+
+    /// Wrap the struct in its enum variant.
+    fn from_variant<V: EnumGenVariant<Enum = Self>>(variant: V) -> Self {
+        variant.into_enum()
+    }
+}
@@ -9,88 +9,759 @@ extern crate syn;
 #[macro_use]
 extern crate quote;
 
-use lazy_static::lazy_static;
-use proc_macro2::{Group, Ident, Literal, Span, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, Group, Ident, Literal, Span, TokenStream, TokenTree};
 use quote::{ToTokens, TokenStreamExt};
-use std::{collections::HashMap, str::FromStr, sync::Mutex};
-use syn::{parse_quote, Attribute, Field, Meta, Variant};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use syn::{
+    braced, bracketed,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream, Parser},
+    parse_quote,
+    punctuated::Punctuated,
+    Attribute, Field, Fields, GenericParam, Generics, LitInt, Meta, Path, PathArguments, Token,
+    Type, Variant,
+};
+
+/// Get the value range of a primitive integer type. None if `ty` is not a
+/// primitive integer type (e.g. it's an alias).
+fn int_type_range(ty: &Type) -> Option<(i128, i128)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let ident = path.path.get_ident()?;
+
+    Some(match ident.to_string().as_str() {
+        "u8" => (u8::MIN.into(), u8::MAX.into()),
+        "u16" => (u16::MIN.into(), u16::MAX.into()),
+        "u32" => (u32::MIN.into(), u32::MAX.into()),
+        "u64" => (u64::MIN.into(), u64::MAX.into()),
+        "u128" => (0, i128::MAX),
+        "usize" => (0, usize::MAX as i128),
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "i64" => (i64::MIN.into(), i64::MAX.into()),
+        "i128" => (i128::MIN, i128::MAX),
+        "isize" => (isize::MIN as i128, isize::MAX as i128),
+        _ => return None,
+    })
+}
 
-#[allow(clippy::from_str_radix_10)]
-fn parse_int(str: &str) -> Result<usize, std::num::ParseIntError> {
-    if let Some(str) = str.strip_prefix("0x") {
-        usize::from_str_radix(str, 16)
-    } else {
-        usize::from_str_radix(str, 10)
+/// Get an ID literal that's typed with `id_type`, if possible. This prevents
+/// matching IDs of a different type.
+fn id_literal(id: i128, id_type: Option<&Type>) -> Literal {
+    match id_type {
+        Some(ty) if int_type_range(ty).is_some() => {
+            let ty = ty.to_token_stream();
+            format!("{id}{ty}").parse().unwrap()
+        }
+        _ => Literal::i128_unsuffixed(id),
     }
 }
 
-// State shared between #[enum_gen] and #[enum_gen_match] calls
-struct GlobalState {
-    enums: HashMap<String, EnumRef>,
-    pending_match_fns: HashMap<String, Vec<EnumMatchFn>>,
+/// Saved data about the generated (final) enum. This is embedded into the
+/// callback macro generated by #[enum_gen], then passed back to
+/// #[enum_gen_match_*] and parsed again. The enum-wide options are enclosed
+/// in brackets. E.g:
+///
+/// `[id_type = u16, module = payload] Hello(Hello) = 43, Goodbye(Goodbye) = 66, Invalid(Invalid) = _ capture`
+///
+/// Variants of exported enums with `#[cfg(...)]` are marked with `?` instead,
+/// e.g. `? Legacy(Legacy) = 44`, see [`cfg_helper_ident`].
+struct EnumRef {
+    /// Explicitly specified type of the IDs
+    id_type: Option<Type>,
+    /// Module with the generated structs, next to the enum
+    module: Option<Ident>,
+    variants: Vec<EnumVariantRef>,
 }
 
-impl GlobalState {
-    pub fn new() -> Self {
-        GlobalState {
-            enums: HashMap::new(),
-            pending_match_fns: HashMap::new(),
-        }
+impl EnumRef {
+    /// The enum-wide options, in brackets.
+    fn options(&self) -> TokenStream {
+        let id_type = self.id_type.iter();
+        let module = self.module.iter();
+        quote!([#(id_type = #id_type,)* #(module = #module,)*])
     }
 }
 
-lazy_static! {
-    static ref CACHE: Mutex<GlobalState> = Mutex::new(GlobalState::new());
+impl ToTokens for EnumRef {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let variants = &self.variants;
+        tokens.extend(self.options());
+        tokens.extend(quote!(#(#variants),*))
+    }
 }
 
-/// Saved data about the generated (final) enum
-#[derive(Debug, Clone)]
-struct EnumRef {
-    name: String,
-    variants: Vec<EnumVariantRef>,
+impl Parse for EnumRef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut id_type = None;
+        let mut module = None;
+
+        let options;
+        bracketed!(options in input);
+        while !options.is_empty() {
+            let key: Ident = options.parse()?;
+            options.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "id_type" => id_type = Some(options.parse()?),
+                "module" => module = Some(options.parse()?),
+                _ => return Err(syn::Error::new(key.span(), "Unknown enum option")),
+            }
+            options.parse::<Token![,]>()?;
+        }
+
+        let variants: Punctuated<EnumVariantRef, Token![,]> = Punctuated::parse_terminated(input)?;
+
+        Ok(EnumRef {
+            id_type,
+            module,
+            variants: variants.into_iter().collect(),
+        })
+    }
 }
 
 /// Enum variant in the generated (final) enum
-#[derive(Debug, Clone)]
 struct EnumVariantRef {
     id: EnumVariantId,
-    name: String,
+    name: Ident,
+    /// Name of the generated struct
+    struct_name: Ident,
+    /// `#[cfg(...)]` attributes of the variant, to be applied to its match arms
+    cfgs: Vec<Attribute>,
+    /// The cfgs are resolved by a helper macro next to the enum instead, as
+    /// they'd be evaluated in the crate calling the callback macro
+    cfg_helper: bool,
+    /// The default variant stores the unmatched ID in its `id` field
+    capture_id: bool,
+}
+
+impl EnumVariantRef {
+    /// The variant without its `#[cfg(...)]` attributes.
+    fn entry(&self) -> TokenStream {
+        let name = &self.name;
+        let struct_name = &self.struct_name;
+        let id = &self.id;
+        let mut tokens = quote!(#name(#struct_name) = #id);
+        if self.capture_id {
+            tokens.append(format_ident!("capture"));
+        }
+        tokens
+    }
+}
+
+impl ToTokens for EnumVariantRef {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let entry = self.entry();
+        match self.cfg_helper {
+            true => tokens.extend(quote!(? #entry)),
+            false => {
+                let cfgs = &self.cfgs;
+                tokens.extend(quote!(#(#cfgs)* #entry));
+            }
+        }
+    }
+}
+
+impl Parse for EnumVariantRef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let cfgs = input.call(Attribute::parse_outer)?;
+        let cfg_helper = input.parse::<Option<Token![?]>>()?.is_some();
+        let name = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        let struct_name = content.parse()?;
+        input.parse::<Token![=]>()?;
+        let id = input.parse()?;
+        let capture_id = input.peek(syn::Ident);
+        if capture_id {
+            let capture: Ident = input.parse()?;
+            if capture != "capture" {
+                return Err(syn::Error::new(capture.span(), "Unknown variant option"));
+            }
+        }
+        Ok(EnumVariantRef {
+            id,
+            name,
+            struct_name,
+            cfgs,
+            cfg_helper,
+            capture_id,
+        })
+    }
 }
 
 /// Enum variant extracted from the original enum.
 struct EnumVariant {
     id: EnumVariantId,
     name: Ident,
-    fields: Vec<Field>,
+    /// Name of the generated struct. The variant name, unless configured otherwise.
+    /// For `use` variants, a hidden alias of the existing type
+    struct_name: Ident,
+    /// `use = path::Type`. Existing type to wrap, instead of generating a struct
+    use_path: Option<Path>,
+    /// Named, unnamed (tuple), or no fields at all. The generated struct has
+    /// the same shape
+    fields: Fields,
+    /// `capture` or `capture(id, bytes)`. Only for the default variant
+    capture: Option<Capture>,
+    /// `#[cfg(...)]` attributes, applied to the variant, its struct, and the
+    /// match arms
+    cfgs: Vec<Attribute>,
+    /// Attributes for the struct only. Includes the doc comments
+    struct_attrs: Vec<Attribute>,
+    /// Attributes for the enum variant only. Includes the doc comments
+    variant_attrs: Vec<Attribute>,
+    /// Struct attributes from #[attr(...)], e.g. `derive(Clone)`. These replace
+    /// the enum-wide attributes of the same name
+    struct_attr_overrides: Vec<Attribute>,
+    /// `include(Position)` or `include(pos: Position)`. Structs embedded as
+    /// fields, after the header and before the variant's own fields
+    includes: Vec<Field>,
+    /// Generics of the enum, limited to the ones used by the variant
+    generics: Generics,
 }
 
-/// ToTokens into the final (generated) enum.
-impl ToTokens for EnumVariant {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
+/// Convert a CamelCase identifier to snake_case. E.g. `AuthBlock` to `auth_block`.
+fn snake_case(ident: &Ident) -> Ident {
+    let name = ident.to_string();
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (idx, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && idx > 0 {
+            let prev = chars[idx - 1];
+            let next_lower = chars.get(idx + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    Ident::new(&snake, ident.span())
+}
+
+/// Parse the contents of `include(...)` into fields, with inherited visibility.
+/// The field is named after the type, unless the name is given.
+fn parse_includes(input: ParseStream) -> syn::Result<Vec<Field>> {
+    let content;
+    parenthesized!(content in input);
+    let mut fields = Vec::new();
+    while !content.is_empty() {
+        let name: Option<Ident> =
+            if content.peek(syn::Ident) && content.peek2(Token![:]) && !content.peek2(Token![::]) {
+                let name = content.parse()?;
+                content.parse::<Token![:]>()?;
+                Some(name)
+            } else {
+                None
+            };
+        let ty: Type = content.parse()?;
+        let name = match name {
+            Some(name) => name,
+            None => match &ty {
+                Type::Path(path) if path.qself.is_none() => {
+                    snake_case(&path.path.segments.last().unwrap().ident)
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "Can't name the field after this type. Name it explicitly. E.g: include(pos: Position)",
+                    ))
+                }
+            },
+        };
+        fields.push(Field::parse_named.parse2(quote!(#name: #ty))?);
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+    }
+    Ok(fields)
+}
+
+/// Data of unmatched IDs, which the default variant has fields generated for.
+#[derive(Default)]
+struct Capture {
+    /// `id: T` field
+    id: bool,
+    /// `bytes: Vec<u8>` field
+    bytes: bool,
+}
+
+impl Capture {
+    /// The generated fields, with given visibility.
+    fn fields(&self, vis: &syn::Visibility, id_type: &Type) -> Vec<Field> {
+        let mut fields = Vec::new();
+        if self.id {
+            fields.push(
+                Field::parse_named
+                    .parse2(quote!(#vis id: #id_type))
+                    .unwrap(),
+            );
+        }
+        if self.bytes {
+            fields.push(
+                Field::parse_named
+                    .parse2(quote!(#vis bytes: ::std::vec::Vec<u8>))
+                    .unwrap(),
+            );
+        }
+        fields
+    }
+}
+
+impl EnumVariant {
+    /// Type wrapped by the variant, as seen from the enum. The struct is
+    /// referenced through `module`, if it's generated there.
+    fn struct_type(&self, module: Option<&Ident>) -> TokenStream {
+        let struct_name = &self.struct_name;
+        let module = module.iter();
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        match &self.use_path {
+            Some(use_path) => quote!(#use_path),
+            None => quote!(#(#module::)* #struct_name #ty_generics),
+        }
+    }
+
+    /// Tokens of the variant in the final (generated) enum.
+    fn enum_variant_tokens(&self, module: Option<&Ident>) -> TokenStream {
         let name = &self.name;
-        tokens.extend(quote! {
-            #name (#name)
-        })
+        let cfgs = &self.cfgs;
+        let attrs = &self.variant_attrs;
+        let ty = self.struct_type(module);
+        // The struct might be deprecated along with the variant
+        quote! {
+            #(#cfgs)* #(#attrs)* #[allow(deprecated)] #name (#ty)
+        }
+    }
+}
+
+/// Find the generic parameters (out of `params`) which are mentioned in
+/// `tokens`. Lifetimes are prefixed with `'`.
+fn mentioned_generics(tokens: TokenStream, params: &[String], found: &mut Vec<String>) {
+    let mut iter = tokens.into_iter();
+    while let Some(token) = iter.next() {
+        let name = match token {
+            TokenTree::Group(group) => {
+                mentioned_generics(group.stream(), params, found);
+                continue;
+            }
+            TokenTree::Punct(p) if p.as_char() == '\'' => match iter.next() {
+                Some(TokenTree::Ident(ident)) => format!("'{ident}"),
+                _ => continue,
+            },
+            TokenTree::Ident(ident) => ident.to_string(),
+            _ => continue,
+        };
+
+        if params.contains(&name) && !found.contains(&name) {
+            found.push(name);
+        }
+    }
+}
+
+/// Get the enum generics limited to the parameters used by `fields`, so they
+/// can be used for the variant's struct. Bounds and where-clause predicates
+/// referring to other parameters are dropped.
+fn variant_generics<'a>(
+    enum_generics: &Generics,
+    fields: impl Iterator<Item = &'a Field>,
+) -> Generics {
+    let param_name = |param: &GenericParam| match param {
+        GenericParam::Lifetime(l) => l.lifetime.to_string(),
+        GenericParam::Type(t) => t.ident.to_string(),
+        GenericParam::Const(c) => c.ident.to_string(),
+    };
+    let params: Vec<String> = enum_generics.params.iter().map(param_name).collect();
+    let mentioned = |tokens: TokenStream| {
+        let mut found = Vec::new();
+        mentioned_generics(tokens, &params, &mut found);
+        found
+    };
+
+    let used = mentioned(fields.map(|f| f.ty.to_token_stream()).collect());
+    let only_used = |tokens: TokenStream| mentioned(tokens).iter().all(|p| used.contains(p));
+
+    let mut generics = Generics::default();
+    for param in &enum_generics.params {
+        if !used.contains(&param_name(param)) {
+            continue;
+        }
+
+        let mut param = param.clone();
+        match &mut param {
+            GenericParam::Lifetime(l) => {
+                l.bounds = l
+                    .bounds
+                    .iter()
+                    .filter(|b| only_used(b.to_token_stream()))
+                    .cloned()
+                    .collect();
+            }
+            GenericParam::Type(t) => {
+                t.bounds = t
+                    .bounds
+                    .iter()
+                    .filter(|b| only_used(b.to_token_stream()))
+                    .cloned()
+                    .collect();
+                if t.default
+                    .as_ref()
+                    .is_some_and(|d| !only_used(d.to_token_stream()))
+                {
+                    t.eq_token = None;
+                    t.default = None;
+                }
+            }
+            GenericParam::Const(_) => (),
+        }
+        generics.params.push(param);
+    }
+
+    if let Some(where_clause) = &enum_generics.where_clause {
+        let predicates: Punctuated<_, Token![,]> = where_clause
+            .predicates
+            .iter()
+            .filter(|p| {
+                let found = mentioned(p.to_token_stream());
+                !found.is_empty() && found.iter().all(|p| used.contains(p))
+            })
+            .cloned()
+            .collect();
+        if !predicates.is_empty() {
+            generics.where_clause = Some(syn::WhereClause {
+                where_token: where_clause.where_token,
+                predicates,
+            });
+        }
+    }
+
+    generics
+}
+
+/// Boundary of an ID range.
+#[derive(Debug, Clone)]
+enum IdBound {
+    /// Integer literal, with its value and the literal as written, for
+    /// error messages
+    Lit(i128, String),
+    /// Constant expression, e.g. `opcodes::HELLO` or `BASE + 3`, and its index.
+    /// The expression is evaluated into a hidden constant next to the generated
+    /// struct, so it can be used in patterns. Only #[enum_gen] knows the
+    /// expression itself, the match functions refer to the constant by index.
+    Expr(TokenStream, usize),
+}
+
+impl IdBound {
+    /// Literal ID of given value, e.g. one assigned by #[enum_gen(auto_id)].
+    fn lit(id: i128) -> Self {
+        IdBound::Lit(id, id.to_string())
+    }
+
+    /// Name of the hidden constant with expression of given index, for given
+    /// struct. It's not an associated constant, as those can't be used in
+    /// patterns if the struct is generic. The struct name is kept verbatim,
+    /// as upper-casing it could make two structs share the same constant.
+    fn const_ident(struct_name: &Ident, idx: usize) -> Ident {
+        format_ident!("__ENUM_GEN_{}_ID_{}", struct_name, idx)
+    }
+
+    /// Tokens evaluating to this bound, usable in patterns. Literals are
+    /// typed with `id_type` if possible, and expressions are referenced
+    /// through the constant with path given by `const_path`.
+    fn to_value(
+        &self,
+        id_type: Option<&Type>,
+        const_path: &dyn Fn(usize) -> TokenStream,
+        span: Span,
+    ) -> TokenStream {
+        match self {
+            IdBound::Lit(id, _) => {
+                let mut lit = id_literal(*id, id_type);
+                lit.set_span(span);
+                lit.into_token_stream()
+            }
+            IdBound::Expr(_, idx) => const_path(*idx),
+        }
+    }
+
+    /// The following ID, as assigned by #[enum_gen(auto_id)]. Expressions are
+    /// renumbered as the first one of a variant.
+    fn next(&self) -> IdBound {
+        match self {
+            IdBound::Lit(id, _) => IdBound::lit(id.saturating_add(1)),
+            IdBound::Expr(expr, _) => IdBound::Expr(quote!((#expr) + 1), 0),
+        }
+    }
+
+    /// Parse the bound from #[attr(ID = ...)]. Plain integer literals are
+    /// evaluated right away, anything else is treated as a constant expression
+    /// and numbered with `next_idx`.
+    fn parse_attr(input: ParseStream, next_idx: &mut usize) -> syn::Result<Self> {
+        let span = input.span();
+        let mut expr = TokenStream::new();
+        while !input.is_empty()
+            && !input.peek(Token![,])
+            && !input.peek(Token![|])
+            && !input.peek(Token![..=])
+        {
+            expr.append(input.parse::<TokenTree>()?);
+        }
+
+        if expr.is_empty() {
+            return Err(syn::Error::new(span, "Expected an ID"));
+        }
+
+        // A comma ends the ID, even inside generic arguments. Unless another
+        // attribute follows, the expression was most likely cut in half
+        if input.peek(Token![,]) && !is_attr_next(&input.fork())? {
+            return Err(syn::Error::new_spanned(
+                expr,
+                "Expected an attribute after the comma. IDs containing commas, e.g. in \
+                    generic arguments, need to be wrapped in parentheses. E.g: ID = (foo::<A, B>())",
+            ));
+        }
+
+        match syn::parse2::<LitInt>(expr.clone()) {
+            // Suffixed literals are left to the compiler to check the type
+            Ok(lit) if lit.suffix().is_empty() => {
+                Ok(IdBound::Lit(lit.base10_parse()?, lit.to_string()))
+            }
+            _ => {
+                let idx = *next_idx;
+                *next_idx += 1;
+                Ok(IdBound::Expr(expr, idx))
+            }
+        }
+    }
+}
+
+/// Check if the input, starting with a comma, continues with another attribute
+/// of #[attr(...)], e.g. `, repr(C)` or `, struct = Name`.
+fn is_attr_next(input: ParseStream) -> syn::Result<bool> {
+    input.parse::<Token![,]>()?;
+    if input.is_empty() {
+        return Ok(true);
+    }
+    if input.call(Ident::parse_any).is_err() {
+        return Ok(false);
+    }
+    while input.peek(Token![::]) {
+        input.parse::<Token![::]>()?;
+        if input.call(Ident::parse_any).is_err() {
+            return Ok(false);
+        }
+    }
+    Ok(input.is_empty()
+        || input.peek(Token![,])
+        || input.peek(Token![=])
+        || input.peek(syn::token::Paren))
+}
+
+impl std::fmt::Display for IdBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdBound::Lit(_, lit) => write!(f, "{}", lit),
+            IdBound::Expr(expr, _) => write!(f, "{}", expr),
+        }
+    }
+}
+
+/// ToTokens into EnumRef, where expressions are saved as `[idx]`.
+impl ToTokens for IdBound {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            IdBound::Lit(id, _) => tokens.append(Literal::i128_unsuffixed(*id)),
+            IdBound::Expr(_, idx) => {
+                let idx = Literal::usize_unsuffixed(*idx);
+                tokens.extend(quote!([#idx]));
+            }
+        }
+    }
+}
+
+/// Parse from EnumRef.
+impl Parse for IdBound {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::token::Bracket) {
+            let content;
+            bracketed!(content in input);
+            let idx: LitInt = content.parse()?;
+            Ok(IdBound::Expr(TokenStream::new(), idx.base10_parse()?))
+        } else {
+            let id: LitInt = input.parse()?;
+            Ok(IdBound::Lit(id.base10_parse()?, id.to_string()))
+        }
+    }
+}
+
+/// A single ID, or an inclusive range of IDs.
+#[derive(Debug, Clone)]
+struct IdRange {
+    start: IdBound,
+    /// None for a single ID
+    end: Option<IdBound>,
+    span: Span,
+}
+
+impl IdRange {
+    fn end(&self) -> &IdBound {
+        self.end.as_ref().unwrap_or(&self.start)
+    }
+
+    /// The range values, if they're both known at macro expansion time.
+    fn lit_bounds(&self) -> Option<(i128, i128)> {
+        match (&self.start, self.end()) {
+            (IdBound::Lit(start, _), IdBound::Lit(end, _)) => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    /// Constant expressions used by this range, with their indices.
+    fn exprs(&self) -> impl Iterator<Item = (&TokenStream, usize)> {
+        [Some(&self.start), self.end.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|bound| match bound {
+                IdBound::Expr(expr, idx) => Some((expr, *idx)),
+                IdBound::Lit(..) => None,
+            })
+    }
+
+    /// Pattern matching this range, with IDs typed with `id_type` if possible.
+    fn to_pattern(
+        &self,
+        id_type: Option<&Type>,
+        const_path: &dyn Fn(usize) -> TokenStream,
+        span: Span,
+    ) -> TokenStream {
+        let start = self.start.to_value(id_type, const_path, span);
+        match &self.end {
+            None => start,
+            Some(end) => {
+                let end = end.to_value(id_type, const_path, span);
+                quote!(#start..=#end)
+            }
+        }
+    }
+
+    /// Parse the range with given function for parsing its bounds.
+    fn parse_with(
+        input: ParseStream,
+        parse_bound: &mut dyn FnMut(ParseStream) -> syn::Result<IdBound>,
+    ) -> syn::Result<Self> {
+        let span = input.span();
+        let start = parse_bound(input)?;
+        let end = if input.peek(Token![..=]) {
+            input.parse::<Token![..=]>()?;
+            Some(parse_bound(input)?)
+        } else {
+            None
+        };
+
+        let range = IdRange { start, end, span };
+        if let Some((start, end)) = range.lit_bounds() {
+            if start > end {
+                return Err(syn::Error::new(span, "Empty ID range"));
+            }
+        }
+        Ok(range)
+    }
+}
+
+impl std::fmt::Display for IdRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.end {
+            None => write!(f, "{}", self.start),
+            Some(end) => write!(f, "{}..={}", self.start, end),
+        }
+    }
+}
+
+impl ToTokens for IdRange {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.start.to_tokens(tokens);
+        if let Some(end) = &self.end {
+            tokens.extend(quote!(..=#end));
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum EnumVariantId {
-    /// Regular match case
-    Val(usize),
+    /// Regular match case. Can be matched by multiple IDs or ranges of IDs
+    Val(Vec<IdRange>),
     /// Default match case
     Default,
 }
 
+impl EnumVariantId {
+    /// Pattern matching this ID, with IDs typed with `id_type` if possible.
+    /// Constant expressions are referenced with paths given by `const_path`.
+    fn to_pattern(
+        &self,
+        id_type: Option<&Type>,
+        const_path: &dyn Fn(usize) -> TokenStream,
+        span: Span,
+    ) -> TokenStream {
+        match self {
+            EnumVariantId::Val(ranges) => {
+                let ranges = ranges
+                    .iter()
+                    .map(|r| r.to_pattern(id_type, const_path, span));
+                quote!(#(#ranges)|*)
+            }
+            EnumVariantId::Default => quote!(_),
+        }
+    }
+
+    /// Parse the ID with given function for parsing the range bounds.
+    fn parse_with(
+        input: ParseStream,
+        parse_bound: &mut dyn FnMut(ParseStream) -> syn::Result<IdBound>,
+    ) -> syn::Result<Self> {
+        if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            return Ok(EnumVariantId::Default);
+        }
+
+        let mut ranges = vec![IdRange::parse_with(input, parse_bound)?];
+        while input.peek(Token![|]) {
+            input.parse::<Token![|]>()?;
+            ranges.push(IdRange::parse_with(input, parse_bound)?);
+        }
+        Ok(EnumVariantId::Val(ranges))
+    }
+
+    /// Parse the ID from #[attr(ID = ...)].
+    fn parse_attr(input: ParseStream) -> syn::Result<Self> {
+        let mut next_idx = 0;
+        Self::parse_with(input, &mut |input| {
+            IdBound::parse_attr(input, &mut next_idx)
+        })
+    }
+}
+
+/// ToTokens into EnumRef.
 impl ToTokens for EnumVariantId {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            EnumVariantId::Val(id) => tokens.append(Literal::usize_unsuffixed(*id)),
-            EnumVariantId::Default => tokens.append(Ident::new("_", Span::call_site())),
+            EnumVariantId::Val(ranges) => tokens.extend(quote!(#(#ranges)|*)),
+            EnumVariantId::Default => tokens.extend(quote!(_)),
         }
     }
 }
 
+/// Parse from EnumRef.
+impl Parse for EnumVariantId {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Self::parse_with(input, &mut IdBound::parse)
+    }
+}
+
 #[derive(Clone, Copy)]
 enum EnumMatchType {
     /// Match by ID
@@ -99,46 +770,172 @@ enum EnumMatchType {
     Variant,
 }
 
+impl EnumMatchType {
+    /// The matched variable, with given span (hygiene) info.
+    fn ident(&self, span: Span) -> Ident {
+        match &self {
+            EnumMatchType::Id => Ident::new("id", span),
+            EnumMatchType::Variant => Ident::new("self", span),
+        }
+    }
+}
+
 impl ToTokens for EnumMatchType {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        match &self {
-            EnumMatchType::Id => {
-                tokens.append(Ident::new("id", Span::call_site()));
-            }
-            EnumMatchType::Variant => {
-                tokens.append(Ident::new("self", Span::call_site()));
+        tokens.append(self.ident(Span::call_site()));
+    }
+}
+
+/// Make sure the option named `name` wasn't given already. Giving it twice is
+/// most likely a mistake, so it's not silently overridden.
+fn check_duplicate<T>(value: &Option<T>, name: &impl ToTokens) -> syn::Result<()> {
+    match value {
+        Some(_) => Err(syn::Error::new_spanned(
+            name,
+            format!("Duplicate `{}`", name.to_token_stream()),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Get the path to an item defined next to the last segment of `path`.
+/// E.g. `crate::proto::Hello` for `crate::proto::Payload`. The item gets the
+/// span of the last segment, so if it can't be found, the error points at the
+/// path provided by the user.
+fn sibling_path(path: &Path, ident: &Ident) -> Path {
+    let mut path = path.clone();
+    if let Some(mut last) = path.segments.pop() {
+        let mut ident = ident.clone();
+        ident.set_span(last.value().ident.span());
+        last.value_mut().ident = ident;
+        last.value_mut().arguments = PathArguments::None;
+        path.segments.push(last.into_value());
+    }
+    path
+}
+
+/// Get the path to an item generated by #[enum_gen], which is either next to
+/// the enum, or in its `module`.
+fn generated_path(enum_path: &Path, module: Option<&Ident>, ident: &Ident) -> Path {
+    match module {
+        Some(module) => {
+            let mut path = sibling_path(enum_path, module);
+            let mut ident = ident.clone();
+            ident.set_span(path.segments.last().unwrap().ident.span());
+            path.segments.push(ident.into());
+            path
+        }
+        None => sibling_path(enum_path, ident),
+    }
+}
+
+/// Get the visibility for an item in a module, which makes it as visible as
+/// `vis` would outside of the module.
+fn vis_in_module(vis: &syn::Visibility) -> syn::Visibility {
+    match vis {
+        syn::Visibility::Public(_) => vis.clone(),
+        syn::Visibility::Inherited => parse_quote!(pub(super)),
+        syn::Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            let first = &path.segments[0].ident;
+            if path.leading_colon.is_some() || first == "crate" {
+                vis.clone()
+            } else if first == "self" {
+                let rest = path.segments.iter().skip(1);
+                parse_quote!(pub(in super #(::#rest)*))
+            } else {
+                parse_quote!(pub(in super::#path))
             }
-        };
+        }
     }
 }
 
 struct EnumVariantMatch<'a> {
     match_by: EnumMatchType,
-    enum_name: &'a Ident,
+    enum_path: &'a Path,
+    module: Option<&'a Ident>,
+    id_type: Option<&'a Type>,
     variant: &'a EnumVariantRef,
     case: &'a TokenStream,
+    /// `Some` or `Ok` to wrap the result of each arm in
+    wrap: Option<&'a TokenStream>,
+    /// Span of the user code, so the generated identifiers are accessible there
+    span: Span,
 }
 
 impl<'a> ToTokens for EnumVariantMatch<'a> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let enum_name = self.enum_name;
-        let name = Ident::new(&self.variant.name, Span::call_site());
-        let id = &self.variant.id;
+        let enum_path = self.enum_path;
+        let name = &self.variant.name;
+        let struct_name = &self.variant.struct_name;
+        let struct_path = generated_path(enum_path, self.module, struct_name);
+        let id = self.variant.id.to_pattern(
+            self.id_type,
+            &|idx| {
+                let const_ident = IdBound::const_ident(struct_name, idx);
+                generated_path(enum_path, self.module, &const_ident).into_token_stream()
+            },
+            self.span,
+        );
         let case = &self.case;
-
+        let inner = Ident::new("inner", self.span);
+        // Point at the enum path in case the struct can't be found there
+        let path_span = enum_path.segments.last().unwrap().ident.span();
+        let struct_type = Ident::new("EnumStructType", self.span.located_at(path_span));
+        let variant_type = Ident::new("EnumVariantType", self.span);
+
+        // The body is the same for every variant, so it can't be written to
+        // suit just one of them, e.g. `EnumStructType::default()` for a unit struct
+        let cfgs = &self.variant.cfgs;
+        tokens.extend(quote! {
+            #(#cfgs)*
+            #[allow(deprecated, non_upper_case_globals, clippy::default_constructed_unit_structs)]
+        });
         tokens.extend({
             match self.match_by {
-                EnumMatchType::Id => quote! {
-                    #id => {
-                        use #name as EnumStructType;
-                        use #enum_name::#name as EnumVariantType;
-                        #case
-                    },
-                },
+                EnumMatchType::Id => {
+                    // The default variant may capture the unmatched ID, so its
+                    // constructor fills it in
+                    let variant_type = if self.variant.capture_id {
+                        let id = EnumMatchType::Id.ident(self.span);
+                        quote! {
+                            #[allow(non_snake_case, unused_variables)]
+                            let #variant_type = |#inner| {
+                                let mut variant = #enum_path::#name(#inner);
+                                #[allow(irrefutable_let_patterns)]
+                                if let #enum_path::#name(#inner) = &mut variant {
+                                    #inner.id = #id;
+                                }
+                                variant
+                            };
+                        }
+                    } else {
+                        quote! {
+                            #[allow(unused_imports)]
+                            use #enum_path::#name as #variant_type;
+                        }
+                    };
+                    let arm = quote! {
+                        {
+                            #[allow(unused_imports)]
+                            use #struct_path as #struct_type;
+                            #variant_type
+                            #case
+                        }
+                    };
+                    match self.wrap {
+                        Some(wrap) => quote!(#id => #wrap(#arm),),
+                        None => quote!(#id => #arm,),
+                    }
+                }
                 EnumMatchType::Variant => quote! {
-                    #enum_name::#name(inner) => {
-                        use #name as EnumStructType;
-                        use #enum_name::#name as EnumVariantType;
+                    #enum_path::#name(#inner) => {
+                        #[allow(unused_imports)]
+                        use #struct_path as #struct_type;
+                        #[allow(unused_imports)]
+                        use #enum_path::#name as #variant_type;
+                        // The body doesn't need to use it
+                        let _ = &#inner;
                         #case
                     },
                 },
@@ -149,302 +946,2104 @@ impl<'a> ToTokens for EnumVariantMatch<'a> {
 
 struct EnumVariantMatcher<'a> {
     match_by: EnumMatchType,
-    enum_name: &'a Ident,
+    enum_path: &'a Path,
+    module: Option<&'a Ident>,
+    id_type: Option<&'a Type>,
     variants: &'a Vec<EnumVariantRef>,
     case: TokenStream,
+    /// Wrapper for the arms, and the arm for unknown IDs
+    fallback: Option<(TokenStream, TokenStream)>,
+    span: Span,
 }
 
 impl<'a> ToTokens for EnumVariantMatcher<'a> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let mut default_variants = self
-            .variants
-            .iter()
-            .filter(|v| matches!(v.id, EnumVariantId::Default));
-
-        // Print some pretty messages for otherwise hard-to-debug problems
-        let default_variant = default_variants.next().expect(
-            "Default variant must be defined. E.g:\n\
-                    \t#[attr(ID = _)]\n\
-                    Unknown",
-        );
-        if default_variants.next().is_some() {
-            panic!("Only one variant with default ID (_) can be defined.");
-        }
-
-        for variant in self.variants {
-            if let EnumVariantId::Default = variant.id {
-                continue;
-            }
+        // When matching by ID, the default variant needs to be the last one
+        let (default_variants, variants): (Vec<_>, Vec<_>) = self.variants.iter().partition(|v| {
+            matches!(self.match_by, EnumMatchType::Id) && matches!(v.id, EnumVariantId::Default)
+        });
 
+        for variant in variants.into_iter().chain(default_variants) {
             let m = EnumVariantMatch {
                 match_by: self.match_by,
-                enum_name: self.enum_name,
+                enum_path: self.enum_path,
+                module: self.module,
+                id_type: self.id_type,
                 variant,
                 case: &self.case,
+                wrap: self.fallback.as_ref().map(|(wrap, _)| wrap),
+                span: self.span,
             };
             m.to_tokens(tokens);
         }
 
-        let m = EnumVariantMatch {
-            match_by: self.match_by,
-            enum_name: self.enum_name,
-            variant: default_variant,
-            case: &self.case,
-        };
-        m.to_tokens(tokens);
+        if let Some((_, arm)) = &self.fallback {
+            tokens.extend(quote!(_ => #arm,));
+        }
     }
 }
 
-impl TryFrom<Variant> for EnumVariant {
-    type Error = ();
-
-    fn try_from(variant: Variant) -> Result<Self, Self::Error> {
+impl EnumVariant {
+    /// Extract the variant from the original enum. Variants without an explicit
+    /// ID are assigned `auto_id`, if it's provided. Unless the variant names its
+    /// struct explicitly, the struct is named after the variant, with the given
+    /// prefix and suffix.
+    fn from_variant(
+        variant: Variant,
+        enum_ident: &Ident,
+        auto_id: Option<&IdBound>,
+        enum_generics: &Generics,
+        (struct_prefix, struct_suffix): (&str, &str),
+    ) -> syn::Result<Self> {
         let name = variant.ident.clone();
         let mut attrs = variant.attrs;
-        let fields = variant.fields.into_iter().collect();
+        let fields = variant.fields;
 
         // Parse variant's attributes
-        let internal_attrs_idx = attrs
-            .iter()
-            .position(|a| match &a.meta {
-                Meta::List(list) => {
-                    if let Some(ident) = list.path.get_ident() {
-                        *ident == "attr"
+        let mut id: Option<EnumVariantId> = None;
+        let mut capture: Option<(Capture, Span)> = None;
+        let mut struct_name: Option<Ident> = None;
+        let mut use_path: Option<(Path, Span)> = None;
+        let mut includes: Vec<Field> = Vec::new();
+        let mut include_span: Option<Span> = None;
+        let mut struct_attr_overrides: Vec<Attribute> = Vec::new();
+        match attrs.iter().position(|a| a.path().is_ident("attr")) {
+            Some(internal_attrs_idx) => {
+                let internal_attrs = attrs.remove(internal_attrs_idx);
+                let Meta::List(internal_attrs) = internal_attrs.meta else {
+                    return Err(syn::Error::new_spanned(
+                        internal_attrs,
+                        "`attr` attribute needs to describe a list. E.g: #[attr(ID = 0x42)]",
+                    ));
+                };
+
+                internal_attrs.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("ID") {
+                        check_duplicate(&id, &meta.path)?;
+                        id = Some(EnumVariantId::parse_attr(meta.value()?)?);
+                        Ok(())
+                    } else if meta.path.is_ident("capture") {
+                        check_duplicate(&capture, &meta.path)?;
+                        let mut c = Capture::default();
+                        if meta.input.is_empty() || meta.input.peek(Token![,]) {
+                            c.id = true;
+                        } else {
+                            meta.parse_nested_meta(|meta| {
+                                if meta.path.is_ident("id") {
+                                    c.id = true;
+                                } else if meta.path.is_ident("bytes") {
+                                    c.bytes = true;
+                                } else {
+                                    let name = meta.path.to_token_stream().to_string();
+                                    return Err(meta.error(format!(
+                                        "Unknown capture `{name}`. Expected `id` or `bytes`"
+                                    )));
+                                }
+                                Ok(())
+                            })?;
+                        }
+                        capture = Some((c, meta.path.get_ident().unwrap().span()));
+                        Ok(())
+                    } else if meta.path.is_ident("struct") {
+                        check_duplicate(&struct_name, &meta.path)?;
+                        struct_name = Some(meta.value()?.parse()?);
+                        Ok(())
+                    } else if meta.path.is_ident("include") {
+                        include_span = Some(meta.path.get_ident().unwrap().span());
+                        includes.extend(parse_includes(meta.input)?);
+                        Ok(())
+                    } else if meta.path.is_ident("use") {
+                        check_duplicate(&use_path, &meta.path)?;
+                        let span = meta.path.get_ident().unwrap().span();
+                        let path = meta.value()?.call(Path::parse_mod_style)?;
+                        if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                            return Err(meta.error(
+                                "Expected a path to the type, without generic arguments. E.g: use = path::Type",
+                            ));
+                        }
+                        use_path = Some((path, span));
+                        Ok(())
+                    } else if meta.input.is_empty() || meta.input.peek(Token![,]) {
+                        // Most likely a typo, e.g. `captur`. Bare struct attributes
+                        // can still be given with #[struct_attr(...)]
+                        let name = meta.path.to_token_stream().to_string();
+                        Err(meta.error(format!(
+                            "Unknown attribute `{name}`. Struct attributes need arguments, \
+                                e.g. derive(Clone), others can be given with #[struct_attr({name})]"
+                        )))
                     } else {
-                        false
+                        // Anything else is an attribute for the struct, e.g. derive(Clone)
+                        let path = &meta.path;
+                        let mut args = TokenStream::new();
+                        while !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                            args.append(meta.input.parse::<TokenTree>()?);
+                        }
+                        // Parsed on its own, so point the errors at the whole attribute
+                        let attr = quote!(#path #args);
+                        let meta: Meta = syn::parse2(attr.clone())
+                            .map_err(|err| syn::Error::new_spanned(attr, err))?;
+                        struct_attr_overrides.push(parse_quote!(#[#meta]));
+                        Ok(())
+                    }
+                })?;
+            }
+            None if auto_id.is_some() => (),
+            None => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Each enum variant needs to have an attr attribute. E.g: #[attr(ID = 0x42)]",
+                ))
+            }
+        }
+
+        // Route the remaining attributes. By default they're applied to both
+        // the struct and the variant, unless wrapped in #[struct_attr(...)]
+        // or #[variant_attr(...)]
+        let mut cfgs = Vec::new();
+        let mut struct_attrs = Vec::new();
+        let mut variant_attrs = Vec::new();
+        for attr in attrs {
+            let path = attr.path();
+            if path.is_ident("cfg") {
+                cfgs.push(attr);
+            } else if path.is_ident("struct_attr") || path.is_ident("variant_attr") {
+                let list = attr.meta.require_list()?;
+                let metas =
+                    list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+                let dest = if path.is_ident("struct_attr") {
+                    &mut struct_attrs
+                } else {
+                    &mut variant_attrs
+                };
+                dest.extend(
+                    metas
+                        .into_iter()
+                        .map(|meta| -> Attribute { parse_quote!(#[#meta]) }),
+                );
+            } else {
+                struct_attrs.push(attr.clone());
+                variant_attrs.push(attr);
+            }
+        }
+
+        let id = match (id, auto_id) {
+            (Some(id), _) => id,
+            (None, Some(auto_id)) => EnumVariantId::Val(vec![IdRange {
+                start: auto_id.clone(),
+                end: None,
+                span: name.span(),
+            }]),
+            (None, None) => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Missing ID identifier. Each enum variant needs to be assigned an ID. E.g: #[attr(ID = 0x42)]\n\
+                        Alternatively, use #[enum_gen(auto_id)] to assign them automatically.",
+                ))
+            }
+        };
+
+        // Whether there's a default variant decides the generated code, so it
+        // can't depend on cfg
+        if let (EnumVariantId::Default, Some(cfg)) = (&id, cfgs.first()) {
+            return Err(syn::Error::new_spanned(
+                cfg,
+                "The default variant can't be conditionally compiled",
+            ));
+        }
+
+        if let Some((_, span)) = &capture {
+            if !matches!(id, EnumVariantId::Default) {
+                return Err(syn::Error::new(
+                    *span,
+                    "Only the default variant can capture the unmatched ID. E.g: #[attr(ID = _, capture)]",
+                ));
+            }
+            if matches!(fields, Fields::Unnamed(_)) {
+                return Err(syn::Error::new(
+                    *span,
+                    "Captured data is stored in named fields, so it can't be used with a tuple variant",
+                ));
+            }
+        }
+
+        if let Some((_, span)) = &use_path {
+            if !matches!(fields, Fields::Unit) {
+                return Err(syn::Error::new(
+                    *span,
+                    "A variant wrapping an existing type can't have fields. E.g: #[attr(ID = 7, use = path::Type)] Status",
+                ));
+            }
+            if capture.is_some()
+                || struct_name.is_some()
+                || !includes.is_empty()
+                || !struct_attr_overrides.is_empty()
+            {
+                return Err(syn::Error::new(
+                    *span,
+                    "No struct is generated for a variant wrapping an existing type, so it can't be \
+                        given `capture`, `struct`, `include`, or struct attributes",
+                ));
+            }
+            // Referenced through a hidden alias next to the enum, like the
+            // generated structs
+            struct_name = Some(format_ident!(
+                "__EnumGen{}{}",
+                enum_ident,
+                name,
+                span = name.span()
+            ));
+        }
+
+        if let (Some(span), Fields::Unnamed(_)) = (include_span, &fields) {
+            return Err(syn::Error::new(
+                span,
+                "Included structs are stored in named fields, so they can't be used with a tuple variant",
+            ));
+        }
+
+        let generics = variant_generics(enum_generics, fields.iter().chain(&includes));
+        let struct_name = struct_name.unwrap_or_else(|| {
+            format_ident!(
+                "{}{}{}",
+                struct_prefix,
+                name,
+                struct_suffix,
+                span = name.span()
+            )
+        });
+
+        Ok(EnumVariant {
+            id,
+            name,
+            struct_name,
+            use_path: use_path.map(|(path, _)| path),
+            fields,
+            includes,
+            capture: capture.map(|(c, _)| c),
+            cfgs,
+            struct_attrs,
+            struct_attr_overrides,
+            variant_attrs,
+            generics,
+        })
+    }
+}
+
+/// Argument to #[enum_gen(...)] macro that will be passed 1:1
+/// to generated structs. Can be derive(Debug) or just e.g. no_mangle,
+/// so the group is optional.
+struct EnumAttribute {
+    ident: Ident,
+    group: Option<Group>,
+}
+
+/// All arguments passed to #[enum_gen(...)] macro
+struct EnumGenArgs {
+    struct_attrs: Vec<EnumAttribute>,
+    /// `enum_attrs(...)`. Attributes for the regenerated enum only
+    enum_attrs: Vec<Attribute>,
+    /// `id_type = u16`. Type of the IDs, usize by default.
+    id_type: Option<Type>,
+    /// `auto_id` or `auto_id = 0x10`. The ID for the first variant without
+    /// an explicit ID.
+    auto_id: Option<IdBound>,
+    /// `struct_prefix = "..."`. Prepended to the variant name to get the struct name.
+    struct_prefix: String,
+    /// `struct_suffix = "..."`. Appended to the variant name to get the struct name.
+    struct_suffix: String,
+    /// `module = payload`. Module to generate the structs in.
+    module: Option<Ident>,
+    /// `common { seq: u16 }` or `common = Header`. Header of every struct.
+    common: Option<CommonHeader>,
+    /// `accessors`. Generate `id()`, `variant_name()`, `PRIMARY_IDS` and
+    /// `VARIANT_NAMES` for the enum.
+    accessors: bool,
+    /// `traits`. Implement the traits from the enum_gen_traits crate.
+    traits: bool,
+    /// `convert`. Implement `From` and `TryFrom` between the enum and the structs.
+    convert: bool,
+}
+
+/// Header with the fields common for all generated structs. It's stored in
+/// a `header` field, which is the first one in every struct.
+enum CommonHeader {
+    /// Fields of a header struct to generate
+    Fields(syn::FieldsNamed),
+    /// Existing header type
+    Type(Type),
+}
+
+/// Organize enum_gen macro arguments into a struct. Note that only a small
+/// part of arguments are getting parsed, the rest is technically invalid syntax
+/// until it's wrapped in #[] and used to decorate a struct.
+/// For that reason, we don't try to parse it yet.
+impl Parse for EnumGenArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs: Vec<EnumAttribute> = Vec::new();
+        let mut enum_attrs: Vec<Attribute> = Vec::new();
+        let mut id_type: Option<Type> = None;
+        let mut auto_id: Option<IdBound> = None;
+        let mut struct_prefix = String::new();
+        let mut struct_suffix = String::new();
+        let mut module: Option<Ident> = None;
+        let mut common: Option<CommonHeader> = None;
+        let mut accessors = false;
+        let mut traits = false;
+        let mut convert = false;
+
+        while !input.is_empty() {
+            // The macro argument can be derive(Debug) - with brackets,
+            // or without them - e.g. no_mangle
+            let ident: Ident = input.parse().map_err(|e| {
+                syn::Error::new(
+                    e.span(),
+                    "Malformed #[enum_gen(...)] syntax. Expected Ident-s. Example: \n\
+                        \t#[enum_gen(derive(Debug, Default), repr(C, packed))]",
+                )
+            })?;
+
+            // `key = value` arguments are options for enum_gen itself
+            if input.peek(Token![=]) {
+                input.parse::<Token![=]>()?;
+                match ident.to_string().as_str() {
+                    "id_type" => {
+                        check_duplicate(&id_type, &ident)?;
+                        id_type = Some(input.parse()?);
+                    }
+                    "auto_id" => {
+                        check_duplicate(&auto_id, &ident)?;
+                        auto_id = Some(IdBound::parse_attr(input, &mut 0)?);
+                    }
+                    "struct_prefix" => struct_prefix = parse_struct_affix(input, true)?,
+                    "struct_suffix" => struct_suffix = parse_struct_affix(input, false)?,
+                    "module" => {
+                        check_duplicate(&module, &ident)?;
+                        module = Some(input.parse()?);
+                    }
+                    "common" => {
+                        check_duplicate(&common, &ident)?;
+                        common = Some(CommonHeader::Type(input.parse()?));
+                    }
+                    name => {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            format!("Unknown option `{name}`"),
+                        ))
+                    }
+                }
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+
+            let group = if input.is_empty() || input.peek(Token![,]) {
+                let is_flag = match ident.to_string().as_str() {
+                    "auto_id" => {
+                        check_duplicate(&auto_id, &ident)?;
+                        auto_id = Some(IdBound::lit(0));
+                        true
+                    }
+                    "accessors" => {
+                        accessors = true;
+                        true
+                    }
+                    "traits" => {
+                        traits = true;
+                        true
+                    }
+                    "convert" => {
+                        convert = true;
+                        true
                     }
+                    _ => false,
+                };
+                if is_flag {
+                    if !input.is_empty() {
+                        input.parse::<Token![,]>()?;
+                    }
+                    continue;
+                }
+                None
+            } else {
+                match input.parse::<TokenTree>()? {
+                    TokenTree::Group(group) => Some(group),
+                    t => return Err(syn::Error::new(t.span(), "Expected `,`")),
+                }
+            };
+
+            if ident == "common" {
+                let Some(group) = group.filter(|g| g.delimiter() == Delimiter::Brace) else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "Expected the header fields, or the header type. E.g: common { seq: u16 }",
+                    ));
+                };
+                check_duplicate(&common, &ident)?;
+                common = Some(CommonHeader::Fields(syn::parse2(
+                    group.into_token_stream(),
+                )?));
+            } else if ident == "enum_attrs" {
+                let Some(group) = group.filter(|g| g.delimiter() == Delimiter::Parenthesis) else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "Expected a list of attributes. E.g: enum_attrs(derive(Debug))",
+                    ));
+                };
+                let metas =
+                    Punctuated::<Meta, Token![,]>::parse_terminated.parse2(group.stream())?;
+                enum_attrs.extend(
+                    metas
+                        .into_iter()
+                        .map(|meta| -> Attribute { parse_quote!(#[#meta]) }),
+                );
+            } else {
+                attrs.push(EnumAttribute { ident, group });
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(EnumGenArgs {
+            struct_attrs: attrs,
+            enum_attrs,
+            id_type,
+            auto_id,
+            struct_prefix,
+            struct_suffix,
+            module,
+            common,
+            accessors,
+            traits,
+            convert,
+        })
+    }
+}
+
+/// Parse the string for `struct_prefix` or `struct_suffix`. It must be possible
+/// to glue it with any variant name into an identifier.
+fn parse_struct_affix(input: ParseStream, prefix: bool) -> syn::Result<String> {
+    let lit: syn::LitStr = input.parse()?;
+    let value = lit.value();
+    let valid = value.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !(prefix && value.starts_with(|c: char| c.is_numeric()));
+    if !valid {
+        return Err(syn::Error::new(
+            lit.span(),
+            format!("`{value}` can't be a part of the struct name"),
+        ));
+    }
+    Ok(value)
+}
+
+/// Procedural macro to generate structures from enum variants. The variants can
+/// be assigned a numerical ID, which can be automatically matched by functions
+/// attributed with #[`enum_gen_match_id`].
+///
+/// The enum variants can have named data (struct like), unnamed data (tuple like), or
+/// no data at all. The generated structs have the same shape - e.g. `Ping(u32)` generates
+/// `struct Ping(pub u32);`, and a variant without data generates a unit struct.
+///
+/// # Examples
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default), repr(C, packed))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8, b: u64, c: u64, d: u8 },
+///     #[attr(ID = 0x42)]
+///     Goodbye { a: u8, e: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// The `#[attr(ID = ...)]` is a mandatory attribute for every variant (unless `auto_id`
+/// is used, see below). The IDs must be unique, and there can be one `#[attr(ID = _)]`
+/// variant which corresponds to the "default" case. Duplicate IDs are rejected at
+/// compile time:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x42)]
+///     Hello { a: u8 },
+///     #[attr(ID = 0x42)]
+///     Goodbye { a: u8 }, // error: Variants `Hello` and `Goodbye` have the same ID (0x42)
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// Other mistakes are reported as compile errors as well, e.g. a missing `#[attr]`:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     Goodbye { a: u8 }, // error: Each enum variant needs to have an attr attribute
+/// }
+/// ```
+///
+/// A second default variant:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = _)]
+///     Invalid,
+///     #[attr(ID = _)]
+///     Unknown, // error: Only one variant with default ID (_) can be defined.
+/// }
+/// ```
+///
+/// An unknown option:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_typ = u8)] // error: Unknown option `id_typ`
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+/// }
+/// ```
+///
+/// A repeated one:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b, ID = 0x2c)] // error: Duplicate `ID`
+///     Hello { a: u8 },
+/// }
+/// ```
+///
+/// Or anything but an enum:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug))]
+/// pub struct Hello { // error: #[enum_gen] expects enum
+///     a: u8,
+/// }
+/// ```
+///
+/// The errors don't stop at the first one, so all of the above can be fixed at once:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_typ = u8)] // error: Unknown option `id_typ`
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     Goodbye { a: u8 }, // error: Each enum variant needs to have an attr attribute
+///     #[attr(ID = _)]
+///     Invalid,
+///     #[attr(ID = _)]
+///     Unknown, // error: Only one variant with default ID (_) can be defined.
+/// }
+/// ```
+///
+/// This will generate the following code, abbreviated - the derives aren't expanded,
+/// and the `#[allow(...)]` attributes are left out. The `accessors`, `traits`, and
+/// `convert` options described below generate some more:
+/// ```rust
+/// pub enum Payload {
+///     Hello(Hello),
+///     Goodbye(Goodbye),
+///     Invalid(Invalid),
+/// }
+/// #[derive(Debug, Default)]
+/// #[repr(C, packed)]
+/// pub struct Hello {
+///     pub a: u8,
+///     pub b: u64,
+///     pub c: u64,
+///     pub d: u8,
+/// }
+/// impl Hello {
+///     /// The primary ID - the first one specified
+///     pub const ID: usize = 43;
+///     /// All IDs matching this struct
+///     pub const IDS: &'static [::core::ops::RangeInclusive<usize>] = &[43..=43];
+///     /// Check if the ID matches this struct
+///     pub const fn contains_id(id: usize) -> bool {
+///         matches!(id, 43usize)
+///     }
+/// }
+/// #[derive(Debug, Default)]
+/// #[repr(C, packed)]
+/// pub struct Goodbye {
+///     pub a: u8,
+///     pub e: u8,
+/// }
+/// impl Goodbye {
+///     /// The primary ID - the first one specified
+///     pub const ID: usize = 66;
+///     /// All IDs matching this struct
+///     pub const IDS: &'static [::core::ops::RangeInclusive<usize>] = &[66..=66];
+///     /// Check if the ID matches this struct
+///     pub const fn contains_id(id: usize) -> bool {
+///         matches!(id, 66usize)
+///     }
+/// }
+/// #[derive(Debug, Default)]
+/// #[repr(C, packed)]
+/// pub struct Invalid;
+/// // Provides the variants to #[enum_gen_match_*] functions, even in other crates.
+/// // The name is made unique with a hash, and re-exported under the enum's name
+/// #[doc(hidden)]
+/// #[macro_export]
+/// macro_rules! __enum_gen_Payload_cb04099b39d5f630 {
+///     ($($tokens:tt)*) => {
+///         ::enum_gen::__enum_gen_match! {
+///             { [] Hello(Hello) = 43, Goodbye(Goodbye) = 66, Invalid(Invalid) = _ }
+///             $($tokens)*
+///         }
+///     };
+/// }
+/// #[doc(hidden)]
+/// pub use __enum_gen_Payload_cb04099b39d5f630 as Payload;
+/// ```
+///
+/// The IDs are `usize` by default, but any other integer type can be specified with
+/// `id_type` option:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_type = u8, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// let id: u8 = Hello::ID;
+/// ```
+///
+/// IDs which don't fit in the type, including the default `usize`, are rejected at
+/// compile time:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = -1)] // error: ID -1 is out of range for `usize`
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// A variant can be also assigned multiple IDs, or ranges of IDs:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_type = u8, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 1 | 2 | 5)]
+///     Hello { a: u8 },
+///     #[attr(ID = 0x10..=0x1f)]
+///     Vendor { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// assert_eq!(Hello::ID, 1);
+/// assert_eq!(Hello::IDS, &[1..=1, 2..=2, 5..=5]);
+/// assert!(Vendor::contains_id(0x1a));
+/// ```
+///
+/// The `ID` constant always contains the first specified ID, while `IDS` contains
+/// all of them. #[`enum_gen_match_id`] matches the variant by any of its IDs.
+///
+/// Besides integer literals, the IDs can be given by constant expressions, e.g.
+/// named constants:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// mod opcodes {
+///     pub const HELLO: u16 = 0x2b;
+/// }
+/// const BASE: u16 = 0x40;
+///
+/// #[enum_gen(id_type = u16, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = opcodes::HELLO)]
+///     Hello { a: u8 },
+///     #[attr(ID = BASE + 3..=BASE + 5)]
+///     Goodbye { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// assert_eq!(Goodbye::ID, 0x43);
+/// ```
+///
+/// Expressions containing `|` or a top-level `,`, e.g. `foo::<A, B>()` or
+/// `<T<A, B> as Trait>::ID`, need to be wrapped in parentheses. Duplicates among
+/// such IDs are detected when the constants are evaluated.
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// const fn opcode<A, B>() -> usize {
+///     0x2b
+/// }
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = opcode::<u8, u16>())] // error: Expected an attribute after the comma
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// Other variant attributes are applied to both the variant and its struct, so doc
+/// comments or `#[deprecated]` end up in both places. `#[cfg(...)]` additionally
+/// applies to the generated match arms, and variants which are never enabled together
+/// may share an ID. The default variant can't have `#[cfg(...)]`. To apply an
+/// attribute to just one of them, wrap it in `#[struct_attr(...)]` or
+/// `#[variant_attr(...)]`:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     /// Greeting, documented on both the variant and the struct
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[cfg(feature = "legacy")]
+///     #[attr(ID = 0x42)]
+///     LegacyGoodbye { a: u8 },
+///     #[cfg(not(feature = "legacy"))]
+///     #[attr(ID = 0x42)]
+///     Goodbye { a: u8, e: u8 },
+///     #[struct_attr(derive(Clone))]
+///     #[attr(ID = 0x43)]
+///     Ack { seq: u16 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[cfg(feature = "lenient")] // error: The default variant can't be conditionally compiled
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// Fields common for all variants can be put into a header, which is the first field
+/// of every struct. `common { ... }` generates an `{Enum}Header` struct with the
+/// given fields and the enum-wide struct attributes, while `common = path::Type` uses
+/// an existing type. The enum gets `header()` and `header_mut()` functions:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(common { seq: u16, flags: u8 }, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },        // struct Hello { header: PayloadHeader, a: u8 }
+///     #[attr(ID = _)]
+///     Invalid,                // struct Invalid { header: PayloadHeader }
+/// }
+///
+/// let mut payload = Payload::Hello(Hello::default());
+/// payload.header_mut().seq = 1;
+/// assert_eq!(payload.header().seq, 1);
+/// ```
+///
+/// A header can't be borrowed from a packed struct, unless it's an `{Enum}Header` packed
+/// as well. Otherwise `header()` returns a copy of the header, which then needs to be
+/// `Copy`, and `set_header()` replaces `header_mut()`.
+///
+/// Other structs can be embedded with `#[attr(include(...))]`. Each one is stored in a
+/// field named after the type (in snake_case), or given explicitly. These fields go
+/// after the header, and before the variant's own fields:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[derive(Debug, Default, Clone, Copy)]
+/// #[repr(C, packed)]
+/// pub struct Position {
+///     pub x: i32,
+///     pub y: i32,
+/// }
+///
+/// #[enum_gen(derive(Debug, Default), repr(C, packed))]
+/// pub enum Payload {
+///     #[attr(ID = 5, include(Position))]
+///     Move { speed: u16 },    // struct Move { position: Position, speed: u16 }
+///     #[attr(ID = 6, include(from: Position, to: Position))]
+///     Teleport,               // struct Teleport { from: Position, to: Position }
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// assert_eq!(std::mem::offset_of!(Move, speed), 8);
+/// ```
+///
+/// A unit variant can wrap an existing type instead, e.g. one defined in another
+/// crate, with `#[attr(use = path::Type)]`. No struct is generated then. As an
+/// inherent `ID` can't be added to a foreign type, the IDs are provided through a
+/// generated `{Enum}VariantId` trait, which is implemented just for the wrapped
+/// types. Code generic over the generated structs should use the `traits` option
+/// and `EnumGenVariant` instead (see below):
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// mod other {
+///     #[derive(Debug, Default)]
+///     pub struct Status {
+///         pub code: u8,
+///     }
+/// }
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = 7, use = other::Status)]
+///     Status,                 // Status(other::Status)
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// #[enum_gen_match_id(Payload)]
+/// fn new_payload(id: usize) -> Payload {
+///     EnumVariantType(EnumStructType::default())
+/// }
+///
+/// assert_eq!(<other::Status as PayloadVariantId>::ID, 7);
+/// assert_eq!(Hello::ID, 0x2b);
+/// assert!(matches!(new_payload(7), Payload::Status(_)));
+/// ```
+///
+/// With the `convert` option, `From` and `TryFrom` conversions between the enum and
+/// the structs are generated as well. They're opt-in, so they don't conflict with
+/// existing impls. `TryFrom` works also with references, and returns the original
+/// enum (or the reference to it) when the variant doesn't match:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(convert, derive(Debug, Default), enum_attrs(derive(Debug)))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// let mut payload = Payload::from(Hello { a: 1 });
+/// let hello: &mut Hello = (&mut payload).try_into().unwrap();
+/// hello.a = 2;
+/// assert!(<&Invalid>::try_from(&payload).is_err());
+/// let hello = Hello::try_from(payload).unwrap();
+/// assert_eq!(hello.a, 2);
+/// ```
+///
+/// The structs are named after the variants, but they can be given a prefix or a
+/// suffix with `struct_prefix = "..."` and `struct_suffix = "..."`, or renamed one
+/// by one with `#[attr(struct = ...)]`. This allows e.g. two enums with the same
+/// variant names in one module:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(struct_suffix = "Req", derive(Debug, Default))]
+/// pub enum Request {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },        // struct HelloReq
+///     #[attr(ID = 0x42, struct = Bye)]
+///     Goodbye { a: u8 },      // struct Bye
+///     #[attr(ID = _)]
+///     Invalid,                // struct InvalidReq
+/// }
+///
+/// #[enum_gen(struct_suffix = "Resp", derive(Debug, Default))]
+/// pub enum Response {
+///     #[attr(ID = 0x2b)]
+///     Hello { b: u16 },       // struct HelloResp
+///     #[attr(ID = _)]
+///     Invalid,                // struct InvalidResp
+/// }
+///
+/// let req = Request::Hello(HelloReq { a: 1 });
+/// ```
+///
+/// Alternatively, the structs (along with their impls) can be generated in a module
+/// next to the enum, with `module = ...`. The items of the enum's module are
+/// available there, and visibilities are adjusted to be the same as without the
+/// module:
+///
+/// ```rust
+/// mod proto {
+///     use enum_gen::*;
+///
+///     pub struct Flags(pub u8);
+///
+///     #[enum_gen(module = payload)]
+///     pub enum Payload {
+///         #[attr(ID = 0x2b)]
+///         Hello { a: u8, flags: Flags },  // struct payload::Hello
+///         #[attr(ID = _)]
+///         Invalid,                        // struct payload::Invalid
+///     }
+/// }
+///
+/// use proto::{payload, Flags, Payload};
+///
+/// let hello = Payload::Hello(payload::Hello { a: 1, flags: Flags(0) });
+/// assert_eq!(payload::Hello::ID, 0x2b);
+/// ```
+///
+/// The enum can be generic. Each struct gets only the generic parameters (and their
+/// bounds) that its fields use:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload<'a, T: Default> {
+///     #[attr(ID = 0x2b)]
+///     Data { buf: &'a [u8] },   // struct Data<'a>
+///     #[attr(ID = 0x42)]
+///     Value { v: T },           // struct Value<T: Default>
+///     #[attr(ID = _)]
+///     Invalid,                  // struct Invalid;
+/// }
+///
+/// impl<'a, T: Default> Payload<'a, T> {
+///     #[enum_gen_match_id(Payload)]
+///     pub fn default(id: usize) -> Self {
+///         EnumVariantType(EnumStructType::default())
+///     }
+/// }
+/// ```
+///
+/// The struct fields are as visible as the enum, unless declared otherwise. Use e.g.
+/// `pub(crate)`, or `pub(self)` for a private field. Field attributes and doc comments
+/// are kept as well:
+///
+/// ```compile_fail
+/// mod proto {
+///     use enum_gen::*;
+///
+///     #[enum_gen(derive(Debug, Default))]
+///     pub enum Payload {
+///         #[attr(ID = 0x2b)]
+///         Hello {
+///             /// Sequence number
+///             seq: u16,
+///             pub(self) checksum: u32,
+///         },
+///         #[attr(ID = _)]
+///         Invalid,
+///     }
+/// }
+///
+/// let hello = proto::Hello::default();
+/// let seq = hello.seq;
+/// let checksum = hello.checksum; // error: field `checksum` is private
+/// ```
+///
+/// Struct attributes can be also specified per variant, inside `#[attr(...)]`. They
+/// replace the enum-wide attributes of the same name, and extend the rest. Attributes
+/// for the regenerated enum only can be given with `enum_attrs(...)`:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default), repr(C, packed), enum_attrs(derive(Debug)))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b, derive(Debug, Default, Clone, Copy))]
+///     Hello { a: u8, b: u64 },
+///     // Can't be packed
+///     #[attr(ID = 0x42, repr(C))]
+///     List { items: Vec<u32> },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// Only attributes with arguments, like `derive(...)` or `doc = "..."`, are passed to
+/// the struct this way. Any other name is most likely a typo, so it's an error.
+/// Attributes without arguments can be given with `#[struct_attr(...)]` instead:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = _, captur)] // error: Unknown attribute `captur`
+///     Invalid,
+/// }
+/// ```
+///
+/// Malformed ones are reported as well:
+///
+/// ```compile_fail
+/// use enum_gen::*;
+///
+/// #[enum_gen(derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b, repr = )] // error: unexpected end of input, expected an expression
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+/// ```
+///
+/// The default variant can capture the data of unmatched IDs in generated fields.
+/// `#[attr(ID = _, capture)]` adds an `id` field of the ID type, and
+/// `#[attr(ID = _, capture(id, bytes))]` adds also a `bytes: Vec<u8>` field for the
+/// raw, unparsed payload. In #[`enum_gen_match_id`] functions, `EnumVariantType(...)`
+/// of the default variant fills in the captured `id`, but the bytes are left to the
+/// caller, which knows where the data comes from:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_type = u8, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = _, capture(id, bytes))]
+///     Unknown,
+/// }
+///
+/// #[enum_gen_match_id(Payload)]
+/// fn new_payload(id: u8) -> Payload {
+///     EnumVariantType(EnumStructType::default())
+/// }
+///
+/// fn parse(id: u8, data: &[u8]) -> Payload {
+///     let mut payload = new_payload(id);
+///     if let Payload::Unknown(unknown) = &mut payload {
+///         unknown.bytes = data.to_vec();
+///     }
+///     payload
+/// }
+///
+/// let Payload::Unknown(unknown) = parse(0x10, &[1, 2]) else { unreachable!() };
+/// assert_eq!((unknown.id, unknown.bytes.as_slice()), (0x10, &[1, 2][..]));
+/// ```
+///
+/// With the `accessors` option, the enum gets `id()` and `variant_name()` functions,
+/// and `PRIMARY_IDS` and `VARIANT_NAMES` constants. They're opt-in, so they don't
+/// collide with the enum's own functions. The ID of the default variant is known only
+/// if it's captured, otherwise `id()` returns an `Option`:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(accessors, id_type = u8, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = 1 | 2)]
+///     Ping,
+///     #[attr(ID = _, capture)]
+///     Unknown,
+/// }
+///
+/// assert_eq!(Payload::PRIMARY_IDS, &[0x2b, 1]);
+/// assert_eq!(Payload::VARIANT_NAMES, &["Hello", "Ping", "Unknown"]);
+/// assert_eq!(Payload::Ping(Ping).id(), 1);
+/// assert_eq!(Payload::Unknown(Unknown { id: 7 }).id(), 7);
+/// assert_eq!(Payload::Ping(Ping).variant_name(), "Ping");
+/// assert_eq!(Ping::IDS, &[1..=1, 2..=2]);
+/// ```
+///
+/// Note the constant is `PRIMARY_IDS`, not `IDS`. It holds just the first ID of each
+/// variant, while the `IDS` constants of the structs hold all of them.
+///
+/// With the `traits` option, the enum and its structs also implement the `EnumGen` and
+/// `EnumGenVariant` traits from the enum_gen_traits crate, so the code can be generic
+/// over them. It needs to be added as a dependency:
+///
+/// ```rust
+/// use enum_gen::*;
+/// use enum_gen_traits::{EnumGen, EnumGenVariant};
+///
+/// #[enum_gen(traits, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = _)]
+///     Invalid,
+/// }
+///
+/// fn wrap<V: EnumGenVariant>(v: V) -> V::Enum {
+///     V::Enum::from_variant(v)
+/// }
+///
+/// let payload: Payload = wrap(Hello { a: 1 });
+/// assert_eq!(EnumGen::id(&payload), Some(<Hello as EnumGenVariant>::ID));
+/// ```
+///
+/// With the `auto_id` option, the variants without an explicit ID are numbered
+/// automatically, C-style - starting from 0 (or the value given as `auto_id = 0x10`),
+/// and continuing from the last ID of the previous variant:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(auto_id, derive(Debug, Default))]
+/// pub enum Command {
+///     Start,
+///     Stop,
+///     #[attr(ID = 0x10)]
+///     Reset,
+///     Status,
+///     #[attr(ID = _)]
+///     Unknown,
+/// }
+///
+/// assert_eq!(Stop::ID, 1);
+/// assert_eq!(Status::ID, 0x11);
+/// ```
+///
+/// The IDs aren't particularly useful on their own, but can be grealy leveraged
+/// with another #[enum_gen_match_id] proc macro.  See its documentation for details.
+#[proc_macro_attribute]
+pub fn enum_gen(
+    attr: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let attr: TokenStream = attr.into();
+    // Identical enums can be defined in different modules, so include the
+    // location as well. The span's debug output contains its source position.
+    let mut hasher = DefaultHasher::new();
+    attr.to_string().hash(&mut hasher);
+    input.to_string().hash(&mut hasher);
+    format!("{:?}", Span::call_site()).hash(&mut hasher);
+    let input_hash = hasher.finish();
+
+    match enum_gen_impl(attr, input.into(), input_hash) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Combine multiple errors, so they can be reported all at once.
+fn combine_error(errors: &mut Option<syn::Error>, error: syn::Error) {
+    match errors {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+/// Make sure that every ID is matched by at most one variant. IDs given by
+/// constant expressions, or of variants behind `#[cfg]` (which might never be
+/// enabled together) can't be checked here, so const assertions are returned
+/// for them instead.
+fn check_unique_ids(
+    variants: &[EnumVariant],
+    id_type: &Type,
+    errors: &mut Option<syn::Error>,
+) -> TokenStream {
+    let mut assertions = TokenStream::new();
+    let mut check = |range: &IdRange,
+                     v: &EnumVariant,
+                     prev: &IdRange,
+                     prev_v: &EnumVariant,
+                     span: Span,
+                     msg: String| {
+        let cfgs: Vec<&Attribute> = if std::ptr::eq(v, prev_v) {
+            v.cfgs.iter().collect()
+        } else {
+            v.cfgs.iter().chain(&prev_v.cfgs).collect()
+        };
+
+        match (range.lit_bounds(), prev.lit_bounds()) {
+            (Some((start, end)), Some((prev_start, prev_end))) if cfgs.is_empty() => {
+                if start <= prev_end && prev_start <= end {
+                    combine_error(errors, syn::Error::new(span, msg));
                 }
-                _ => false,
-            })
-            .expect("Each enum variant needs to be have an attr attribute. #[attr(ID = 0x42)]");
-        let internal_attrs = attrs.remove(internal_attrs_idx);
-        let Meta::List(internal_attrs) = internal_attrs.meta else {
-            panic!("`attr` attribute needs to describe a list. E.g: #[attr(ID = 0x42)]");
+            }
+            (Some((start, end)), Some((prev_start, prev_end)))
+                if start > prev_end || prev_start > end => {}
+            _ => {
+                let const_path =
+                    |idx| IdBound::const_ident(&v.struct_name, idx).into_token_stream();
+                let prev_const_path =
+                    |idx| IdBound::const_ident(&prev_v.struct_name, idx).into_token_stream();
+                let start = range.start.to_value(Some(id_type), &const_path, range.span);
+                let end = range.end().to_value(Some(id_type), &const_path, range.span);
+                let prev_start = prev
+                    .start
+                    .to_value(Some(id_type), &prev_const_path, range.span);
+                let prev_end = prev
+                    .end()
+                    .to_value(Some(id_type), &prev_const_path, range.span);
+                assertions.extend(quote_spanned! {range.span=>
+                    #(#cfgs)*
+                    const _: () = ::core::assert!(
+                        !(#start <= #prev_end && #prev_start <= #end),
+                        "{}",
+                        #msg
+                    );
+                });
+            }
+        }
+    };
+
+    for (idx, v) in variants.iter().enumerate() {
+        let EnumVariantId::Val(ranges) = &v.id else {
+            continue;
         };
 
-        let mut tokens_iter = internal_attrs.tokens.into_iter();
-        let mut id: Option<EnumVariantId> = None;
+        for (range_idx, range) in ranges.iter().enumerate() {
+            // Check the previous IDs of this variant
+            for prev in &ranges[..range_idx] {
+                let msg = format!("ID {range} overlaps with {prev} of the same variant");
+                check(range, v, prev, v, range.span, msg);
+            }
 
-        loop {
-            let Some(token) = tokens_iter.next() else {
-                break;
-            };
+            // Check the previous variants
+            for prev in &variants[..idx] {
+                let EnumVariantId::Val(prev_ranges) = &prev.id else {
+                    continue;
+                };
+
+                for prev_range in prev_ranges {
+                    let msg = if prev_range.end.is_none() && range.end.is_none() {
+                        format!(
+                            "Variants `{}` and `{}` have the same ID ({range})",
+                            prev.name, v.name
+                        )
+                    } else {
+                        format!(
+                            "Variants `{}` and `{}` have overlapping IDs ({prev_range} and {range})",
+                            prev.name, v.name
+                        )
+                    };
+                    check(range, v, prev_range, prev, v.name.span(), msg);
+                }
+            }
+        }
+    }
 
-            let TokenTree::Ident(ident) = token else {
+    assertions
+}
+
+/// Make sure that every ID fits in the ID type.
+fn check_id_range(variants: &[EnumVariant], id_type: &Type, errors: &mut Option<syn::Error>) {
+    let Some((min, max)) = int_type_range(id_type) else {
+        // Can't check it, the compiler will complain in case of problems
+        return;
+    };
+
+    for v in variants {
+        let EnumVariantId::Val(ranges) = &v.id else {
+            continue;
+        };
+
+        for range in ranges {
+            // Constant expressions are checked by the compiler
+            let Some((start, end)) = range.lit_bounds() else {
                 continue;
             };
 
-            match ident.to_string().as_str() {
-                "ID" => {
-                    expect_punct_token(tokens_iter.next());
-                    let value = tokens_iter
-                        .next()
-                        .expect("Unknown attr syntax. Expected `#[attr(ID = 0x42)]`");
-
-                    id = Some(match &value {
-                        TokenTree::Ident(ident) => {
-                            if *ident == "_" {
-                                EnumVariantId::Default
-                            } else {
-                                let str = value.to_string();
-                                EnumVariantId::Val(
-                                    parse_int(&str)
-                                        .expect("Invalid ID attribute. Expected a number"),
-                                )
-                            }
-                        }
-                        _ => {
-                            let str = value.to_string();
-                            EnumVariantId::Val(
-                                parse_int(&str).expect("Invalid ID attribute. Expected a number"),
-                            )
-                        }
-                    });
+            if start < min || end > max {
+                combine_error(
+                    errors,
+                    syn::Error::new(
+                        range.span,
+                        format!(
+                            "ID {range} is out of range for `{}`",
+                            id_type.to_token_stream()
+                        ),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Make sure that every existing type is wrapped by at most one variant, as the
+/// IDs and conversions are implemented for the wrapped types.
+fn check_wrapped_types(variants: &[EnumVariant], errors: &mut Option<syn::Error>) {
+    for (idx, v) in variants.iter().enumerate() {
+        let Some(use_path) = &v.use_path else {
+            continue;
+        };
+        let path_str = use_path.to_token_stream().to_string();
+        if let Some(prev) = variants[..idx].iter().find(|prev| {
+            prev.use_path
+                .as_ref()
+                .is_some_and(|p| p.to_token_stream().to_string() == path_str)
+        }) {
+            combine_error(
+                errors,
+                syn::Error::new_spanned(
+                    use_path,
+                    format!("This type is already wrapped by variant `{}`", prev.name),
+                ),
+            );
+        }
+    }
+}
+
+/// Make sure that every variant can have the common header. It's a named
+/// field, and existing types don't have it.
+fn check_common_header(variants: &[EnumVariant], errors: &mut Option<syn::Error>) {
+    for v in variants {
+        let msg = if v.use_path.is_some() {
+            "A variant wrapping an existing type can't have the common header"
+        } else if let Fields::Unnamed(_) = v.fields {
+            "The common header is stored in a named field, so it can't be used with a tuple variant"
+        } else {
+            continue;
+        };
+        combine_error(errors, syn::Error::new(v.name.span(), msg));
+    }
+}
+
+/// The alignment a `#[repr(packed)]` or `#[repr(packed(N))]` attribute limits the
+/// struct to, if any. Malformed attributes are left for the compiler to report.
+fn packed_align<'a>(attrs: impl IntoIterator<Item = &'a Attribute>) -> Option<u64> {
+    let mut align = None;
+    for attr in attrs {
+        if !attr.path().is_ident("repr") {
+            continue;
+        }
+        let _ = attr.parse_nested_meta(|meta| {
+            let parsed = if meta.input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<LitInt>().ok()
+            } else {
+                None
+            };
+            if meta.path.is_ident("packed") {
+                let n = parsed.and_then(|lit| lit.base10_parse().ok()).unwrap_or(1);
+                align = Some(align.map_or(n, |a: u64| a.min(n)));
+            }
+            Ok(())
+        });
+    }
+    align
+}
+
+/// Generate the ID constants of a struct. Existing types get them through the
+/// `{Enum}VariantId` trait instead.
+fn struct_ids(
+    v: &EnumVariant,
+    id_type: &Type,
+    struct_vis: &syn::Visibility,
+    variant_id_trait: Option<&Ident>,
+) -> TokenStream {
+    let EnumVariantId::Val(ranges) = &v.id else {
+        return TokenStream::new();
+    };
+    let name = &v.struct_name;
+    let cfgs = &v.cfgs;
+    let span = Span::call_site();
+    let const_path = |idx| IdBound::const_ident(name, idx).into_token_stream();
+    let consts = ranges.iter().flat_map(IdRange::exprs).map(|(expr, idx)| {
+        let ident = IdBound::const_ident(name, idx);
+        quote! {
+            #(#cfgs)*
+            #[doc(hidden)]
+            #[allow(unused_braces, unused_parens, non_upper_case_globals)]
+            #struct_vis const #ident: #id_type = #expr;
+        }
+    });
+    let primary_id = ranges[0].start.to_value(None, &const_path, span);
+    let starts = ranges
+        .iter()
+        .map(|r| r.start.to_value(None, &const_path, span));
+    let ends = ranges
+        .iter()
+        .map(|r| r.end().to_value(None, &const_path, span));
+    let pattern = v.id.to_pattern(Some(id_type), &const_path, span);
+    let (impl_generics, ty_generics, where_clause) = v.generics.split_for_impl();
+
+    let mut tokens = quote!(#(#consts)*);
+    match (&v.use_path, variant_id_trait) {
+        (None, _) => tokens.extend(quote! {
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The primary ID - the first one specified
+                pub const ID: #id_type = #primary_id;
+                /// All IDs matching this struct
+                pub const IDS: &'static [::core::ops::RangeInclusive<#id_type>] = &[#(#starts..=#ends),*];
+
+                /// Check if the ID matches this struct
+                #[allow(non_upper_case_globals)]
+                pub const fn contains_id(id: #id_type) -> bool {
+                    matches!(id, #pattern)
+                }
+            }
+        }),
+        (Some(_), Some(variant_id_trait)) => tokens.extend(quote! {
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #impl_generics #variant_id_trait for #name #ty_generics #where_clause {
+                const ID: #id_type = #primary_id;
+                const IDS: &'static [::core::ops::RangeInclusive<#id_type>] = &[#(#starts..=#ends),*];
+            }
+        }),
+        (Some(_), None) => (),
+    }
+    tokens
+}
+
+/// Declare the `{Enum}VariantId` trait, which provides the IDs of existing
+/// types wrapped by the variants, as they can't get an inherent `ID`.
+fn variant_id_trait(
+    enum_ident: &Ident,
+    enum_vis: &syn::Visibility,
+    variant_id_trait: &Ident,
+    id_type: &Type,
+) -> TokenStream {
+    let doc = format!(" IDs of the `{enum_ident}` variants wrapping existing types");
+    quote! {
+        #[doc = #doc]
+        #enum_vis trait #variant_id_trait {
+            /// The primary ID - the first one specified
+            const ID: #id_type;
+            /// All IDs matching this type
+            const IDS: &'static [::core::ops::RangeInclusive<#id_type>];
+
+            /// Check if the ID matches this type
+            fn contains_id(id: #id_type) -> bool {
+                Self::IDS.iter().any(|ids| ids.contains(&id))
+            }
+        }
+    }
+}
+
+/// Generate `header()` and `header_mut()` for the enum, to access the common
+/// header regardless of the variant. If the header may be misaligned in a packed
+/// struct, it can't be borrowed, so it's copied by `header()` and `set_header()`
+/// instead.
+fn header_accessors(
+    variants: &[EnumVariant],
+    enum_ident: &Ident,
+    enum_generics: &syn::Generics,
+    header_type: &Type,
+    by_value: bool,
+) -> TokenStream {
+    let arms = |expr: TokenStream| {
+        variants.iter().map(move |v| {
+            let name = &v.name;
+            let cfgs = &v.cfgs;
+            quote! {
+                #(#cfgs)*
+                #[allow(deprecated)]
+                Self::#name(inner) => #expr,
+            }
+        })
+    };
+    let (impl_generics, ty_generics, where_clause) = enum_generics.split_for_impl();
+    if by_value {
+        let arms_get = arms(quote!(inner.header));
+        let arms_set = arms(quote!(inner.header = header));
+        return quote! {
+            impl #impl_generics #enum_ident #ty_generics #where_clause {
+                /// A copy of the header common for all variants
+                pub fn header(&self) -> #header_type {
+                    match self {
+                        #(#arms_get)*
+                    }
+                }
+
+                /// Replace the header common for all variants
+                pub fn set_header(&mut self, header: #header_type) {
+                    match self {
+                        #(#arms_set)*
+                    }
+                }
+            }
+        };
+    }
+    let arms_ref = arms(quote!(&inner.header));
+    let arms_mut = arms(quote!(&mut inner.header));
+    quote! {
+        impl #impl_generics #enum_ident #ty_generics #where_clause {
+            /// The header common for all variants
+            pub fn header(&self) -> &#header_type {
+                match self {
+                    #(#arms_ref)*
+                }
+            }
+
+            /// The header common for all variants
+            pub fn header_mut(&mut self) -> &mut #header_type {
+                match self {
+                    #(#arms_mut)*
+                }
+            }
+        }
+    }
+}
+
+/// Match arms and constants for the runtime accessors of the IDs and names,
+/// shared by the inherent ones and the `EnumGen` impl.
+struct EnumAccessors {
+    /// Whether every variant knows its ID. The default variant knows it only
+    /// if it's captured
+    id_known: bool,
+    id_arms: TokenStream,
+    name_arms: TokenStream,
+    primary_ids: TokenStream,
+    variant_names: TokenStream,
+}
+
+impl EnumAccessors {
+    fn new(variants: &[EnumVariant], id_type: &Type, module: Option<&Ident>) -> Self {
+        let id_known = variants.iter().all(|v| match &v.id {
+            EnumVariantId::Val(_) => true,
+            EnumVariantId::Default => v.capture.as_ref().is_some_and(|c| c.id),
+        });
+        let mut id_arms = TokenStream::new();
+        let mut name_arms = TokenStream::new();
+        let mut primary_ids = TokenStream::new();
+        for v in variants {
+            let name = &v.name;
+            let cfgs = &v.cfgs;
+            let name_str = name.to_string();
+            let id = match &v.id {
+                EnumVariantId::Val(ranges) => {
+                    let const_path = |idx| {
+                        let ident = IdBound::const_ident(&v.struct_name, idx);
+                        let module = module.iter();
+                        quote!(#(#module::)* #ident)
+                    };
+                    let id =
+                        ranges[0]
+                            .start
+                            .to_value(Some(id_type), &const_path, Span::call_site());
+                    primary_ids.extend(quote!(#(#cfgs)* #id,));
+                    Some(id)
+                }
+                EnumVariantId::Default if v.capture.as_ref().is_some_and(|c| c.id) => {
+                    Some(quote!(inner.id))
+                }
+                EnumVariantId::Default => None,
+            };
+            let id = match (id, id_known) {
+                (Some(id), true) => id,
+                (Some(id), false) => quote!(::core::option::Option::Some(#id)),
+                (None, _) => quote!(::core::option::Option::None),
+            };
+            id_arms.extend(quote! {
+                #(#cfgs)*
+                #[allow(deprecated, unused_variables)]
+                Self::#name(inner) => #id,
+            });
+            name_arms.extend(quote! {
+                #(#cfgs)*
+                #[allow(deprecated)]
+                Self::#name(_) => #name_str,
+            });
+        }
+        let variant_names = variants.iter().map(|v| {
+            let cfgs = &v.cfgs;
+            let name_str = v.name.to_string();
+            quote!(#(#cfgs)* #name_str)
+        });
+
+        Self {
+            id_known,
+            id_arms,
+            name_arms,
+            primary_ids,
+            variant_names: quote!(&[#(#variant_names),*]),
+        }
+    }
+}
+
+/// Generate `id()`, `variant_name()`, `PRIMARY_IDS` and `VARIANT_NAMES` for
+/// the enum.
+fn enum_accessors(
+    accessors: &EnumAccessors,
+    enum_ident: &Ident,
+    enum_generics: &syn::Generics,
+    id_type: &Type,
+) -> TokenStream {
+    let EnumAccessors {
+        id_known,
+        id_arms,
+        name_arms,
+        primary_ids,
+        variant_names,
+    } = accessors;
+    let id_ret = match id_known {
+        true => quote!(#id_type),
+        false => quote!(::core::option::Option<#id_type>),
+    };
+    let (impl_generics, ty_generics, where_clause) = enum_generics.split_for_impl();
+    quote! {
+        impl #impl_generics #enum_ident #ty_generics #where_clause {
+            /// The primary IDs of all variants, except the default one
+            pub const PRIMARY_IDS: &'static [#id_type] = &[#primary_ids];
+            /// The names of all variants
+            pub const VARIANT_NAMES: &'static [&'static str] = #variant_names;
+
+            /// The primary ID of the variant, or the ID captured by the default variant
+            pub fn id(&self) -> #id_ret {
+                match self {
+                    #id_arms
                 }
-                name => {
-                    panic!("Unknown attribute `{name}`")
+            }
+
+            /// The name of the variant
+            pub fn variant_name(&self) -> &'static str {
+                match self {
+                    #name_arms
+                }
+            }
+        }
+    }
+}
+
+/// Implement the traits from the enum_gen_traits crate. They're implemented
+/// for structs with the same generics as the enum, as the rest can't name it.
+/// Existing types might be foreign, so they're skipped.
+fn trait_impls(
+    accessors: &EnumAccessors,
+    variants: &[EnumVariant],
+    enum_ident: &Ident,
+    enum_generics: &syn::Generics,
+    id_type: &Type,
+    module: Option<&Ident>,
+) -> TokenStream {
+    let EnumAccessors {
+        id_known,
+        id_arms,
+        name_arms,
+        primary_ids,
+        variant_names,
+    } = accessors;
+    let id = match id_known {
+        true => quote!(::core::option::Option::Some(match self { #id_arms })),
+        false => quote!(match self { #id_arms }),
+    };
+    let (impl_generics, ty_generics, where_clause) = enum_generics.split_for_impl();
+    let mut tokens = quote! {
+        impl #impl_generics ::enum_gen_traits::EnumGen for #enum_ident #ty_generics #where_clause {
+            type Id = #id_type;
+
+            const PRIMARY_IDS: &'static [#id_type] = &[#primary_ids];
+            const VARIANT_NAMES: &'static [&'static str] = #variant_names;
+
+            fn id(&self) -> ::core::option::Option<#id_type> {
+                #id
+            }
+
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #name_arms
                 }
             }
         }
+    };
 
-        if attrs.len() > 1 {
-            panic!("Currently additional variant attributes are not supported");
+    for v in variants {
+        let EnumVariantId::Val(ranges) = &v.id else {
+            continue;
+        };
+        if v.use_path.is_some() || v.generics.params.len() != enum_generics.params.len() {
+            continue;
         }
 
-        let id = id.expect("Missing ID identifier.Each enum variant needs to be assigned an ID. #[attr(ID = 0x42)]");
-        Ok(EnumVariant { id, name, fields })
+        let name = &v.name;
+        let name_str = name.to_string();
+        let cfgs = &v.cfgs;
+        let ty = v.struct_type(module);
+        let const_path = |idx| {
+            let ident = IdBound::const_ident(&v.struct_name, idx);
+            let module = module.iter();
+            quote!(#(#module::)* #ident)
+        };
+        let span = Span::call_site();
+        let primary_id = ranges[0].start.to_value(None, &const_path, span);
+        let starts = ranges
+            .iter()
+            .map(|r| r.start.to_value(None, &const_path, span));
+        let ends = ranges
+            .iter()
+            .map(|r| r.end().to_value(None, &const_path, span));
+        tokens.extend(quote! {
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #impl_generics ::enum_gen_traits::EnumGenVariant for #ty #where_clause {
+                type Id = #id_type;
+                type Enum = #enum_ident #ty_generics;
+
+                const ID: #id_type = #primary_id;
+                const IDS: &'static [::core::ops::RangeInclusive<#id_type>] = &[#(#starts..=#ends),*];
+                const NAME: &'static str = #name_str;
+
+                fn into_enum(self) -> Self::Enum {
+                    #enum_ident::#name(self)
+                }
+            }
+        });
+    }
+
+    tokens
+}
+
+/// Implement `From` and `TryFrom` between the enum and the structs. `TryFrom`
+/// is implemented for references as well.
+fn conversions(
+    variants: &[EnumVariant],
+    enum_ident: &Ident,
+    enum_generics: &syn::Generics,
+    module: Option<&Ident>,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = enum_generics.split_for_impl();
+    let mut ref_generics = enum_generics.clone();
+    ref_generics.params.insert(0, parse_quote!('__enum_gen));
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+    let enum_ty = quote!(#enum_ident #ty_generics);
+
+    let mut tokens = TokenStream::new();
+    for v in variants {
+        let ty = v.struct_type(module);
+        let name = &v.name;
+        let cfgs = &v.cfgs;
+        tokens.extend(quote! {
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #impl_generics ::core::convert::From<#ty> for #enum_ty #where_clause {
+                fn from(inner: #ty) -> Self {
+                    Self::#name(inner)
+                }
+            }
+
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #impl_generics ::core::convert::TryFrom<#enum_ty> for #ty #where_clause {
+                type Error = #enum_ty;
+
+                fn try_from(value: #enum_ty) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        #enum_ident::#name(inner) => ::core::result::Result::Ok(inner),
+                        #[allow(unreachable_patterns)]
+                        value => ::core::result::Result::Err(value),
+                    }
+                }
+            }
+
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #ref_impl_generics ::core::convert::TryFrom<&'__enum_gen #enum_ty> for &'__enum_gen #ty #where_clause {
+                type Error = &'__enum_gen #enum_ty;
+
+                fn try_from(value: &'__enum_gen #enum_ty) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        #enum_ident::#name(inner) => ::core::result::Result::Ok(inner),
+                        #[allow(unreachable_patterns)]
+                        value => ::core::result::Result::Err(value),
+                    }
+                }
+            }
+
+            #(#cfgs)*
+            #[allow(deprecated)]
+            impl #ref_impl_generics ::core::convert::TryFrom<&'__enum_gen mut #enum_ty> for &'__enum_gen mut #ty #where_clause {
+                type Error = &'__enum_gen mut #enum_ty;
+
+                fn try_from(value: &'__enum_gen mut #enum_ty) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        #enum_ident::#name(inner) => ::core::result::Result::Ok(inner),
+                        #[allow(unreachable_patterns)]
+                        value => ::core::result::Result::Err(value),
+                    }
+                }
+            }
+        });
     }
-}
 
-/// Argument to #[enum_gen(...)] macro that will be passed 1:1
-/// to generated structs. Can be derive(Debug) or just e.g. no_mangle,
-/// so the group is optional.
-struct EnumAttribute {
-    ident: Ident,
-    group: Option<Group>,
+    tokens
 }
 
-fn expect_punct_token(token: Option<TokenTree>) {
-    match token {
-        Some(TokenTree::Punct(punct)) => {
-            if punct.as_char() != '=' {
-                panic!("Unknown parse_fn syntax. Expected `parse_fn = my_fn`");
+/// Generate the `{Enum}UnknownId` error, returned by #[enum_gen_match_id]
+/// functions when the enum has no default variant.
+fn unknown_id_error(enum_ident: &Ident, enum_vis: &syn::Visibility, id_type: &Type) -> TokenStream {
+    let unknown_id = format_ident!("{}UnknownId", enum_ident);
+    let doc = format!(
+        " ID without a matching `{enum_ident}` variant. Returned by #[enum_gen_match_id] functions returning a `Result`."
+    );
+    let msg = format!("Unknown `{enum_ident}` ID: {{}}");
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #enum_vis struct #unknown_id(pub #id_type);
+
+        impl ::core::fmt::Display for #unknown_id {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::write!(f, #msg, self.0)
             }
         }
-        _ => panic!("parse_fn param should be followed by `= my_fn`. E.g. `parse_fn = my_fn`"),
+
+        impl ::core::error::Error for #unknown_id {}
     }
 }
 
-/// All arguments passed to #[enum_gen(...)] macro
-struct EnumGenArgs {
-    struct_attrs: Vec<EnumAttribute>,
+/// Name of the helper macro resolving the cfgs of a variant of an exported
+/// enum. The cfgs of the variants can't be put in the callback macro, as they'd
+/// be evaluated in the crate calling it. Instead, #[enum_gen_match_*] calls
+/// the helper next to the enum, which adds `[Variant = true]` or
+/// `[Variant = false]` after the enum definition, depending on the cfgs in the
+/// crate of the enum.
+fn cfg_helper_ident(enum_ident: &Ident, variant: &Ident) -> Ident {
+    format_ident!("__enum_gen_{}_{}", enum_ident, variant)
 }
 
-/// Organize enum_gen macro arguments into a struct. Note that only a small
-/// part of arguments are getting parsed, the rest is technically invalid syntax
-/// until it's wrapped in #[] and used to decorate a struct.
-/// For that reason, we don't try to parse it yet.
-impl TryFrom<TokenStream> for EnumGenArgs {
-    type Error = ();
-
-    fn try_from(tokens: TokenStream) -> Result<Self, Self::Error> {
-        let mut tokens_iter = tokens.into_iter();
-        let mut attrs: Vec<EnumAttribute> = Vec::new();
-
-        loop {
-            // The macro argument can be derive(Debug) - with brackets,
-            // or without them - e.g. no_mangle
-            let Some(ident) = tokens_iter.next() else {
-                break;
-            };
-            let TokenTree::Ident(ident) = ident else {
-                panic!(
-                    "Malformed #[enum_gen(...)] syntax. Expected Ident-s. Example: \n\
-                        \t#[enum_gen(derive(Debug, Default), repr(C, packed))]"
-                );
+/// Export the variants through a callback macro, which will be called by
+/// #[enum_gen_match_*] functions. It lives in the macro namespace under the
+/// enum's name, so it's found wherever the enum is.
+fn callback_macro(
+    enumref: &EnumRef,
+    enum_ident: &Ident,
+    enum_vis: &syn::Visibility,
+    input_hash: u64,
+) -> TokenStream {
+    match enum_vis {
+        syn::Visibility::Public(_) => {
+            // #[macro_export] is required to use the macro in other crates. It
+            // also puts the macro in the crate root, so give it a unique name.
+            // Exporting it from a function body is fine, as it's only reached
+            // through the re-export next to the enum.
+            let callback_ident = format_ident!("__enum_gen_{}_{:016x}", enum_ident, input_hash);
+            let mut tokens = quote! {
+                #[doc(hidden)]
+                #[macro_export]
+                #[allow(non_local_definitions)]
+                macro_rules! #callback_ident {
+                    ($($tokens:tt)*) => {
+                        ::enum_gen::__enum_gen_match! { { #enumref } $($tokens)* }
+                    };
+                }
+                #[doc(hidden)]
+                #[allow(unused_imports)]
+                pub use #callback_ident as #enum_ident;
             };
 
-            let group = match tokens_iter.next() {
-                Some(TokenTree::Group(group)) => {
-                    let group = group.clone();
-                    // skip the following comma (or nothing)
-                    tokens_iter.next();
-                    Some(group)
+            // The helper macro tells whether the variant is enabled. It's
+            // defined twice, and the cfg picks one
+            for v in enumref.variants.iter().filter(|v| v.cfg_helper) {
+                let name = &v.name;
+                let helper_ident = format_ident!("{}_{}", callback_ident, name);
+                let reexport_ident = cfg_helper_ident(enum_ident, name);
+                let preds = v.cfgs.iter().map(|cfg| match &cfg.meta {
+                    Meta::List(list) => list.tokens.clone(),
+                    meta => meta.to_token_stream(),
+                });
+                let pred = quote!(all(#(#preds),*));
+                for (pred, enabled) in [(pred.clone(), true), (quote!(not(#pred)), false)] {
+                    tokens.extend(quote! {
+                        #[cfg(#pred)]
+                        #[doc(hidden)]
+                        #[macro_export]
+                        #[allow(non_local_definitions)]
+                        macro_rules! #helper_ident {
+                            ({ $($enumref:tt)* } $($tokens:tt)*) => {
+                                ::enum_gen::__enum_gen_match! {
+                                    { $($enumref)* } [#name = #enabled] $($tokens)*
+                                }
+                            };
+                        }
+                    });
                 }
-                _ => {
-                    // we consumed a comma (or nothing)
-                    None
+                tokens.extend(quote! {
+                    #[doc(hidden)]
+                    #[allow(unused_imports)]
+                    pub use #helper_ident as #reexport_ident;
+                });
+            }
+            tokens
+        }
+        vis => {
+            let callback_ident = format_ident!("__enum_gen_{}", enum_ident);
+            quote! {
+                #[doc(hidden)]
+                macro_rules! #callback_ident {
+                    ($($tokens:tt)*) => {
+                        ::enum_gen::__enum_gen_match! { { #enumref } $($tokens)* }
+                    };
                 }
-            };
-
-            attrs.push(EnumAttribute { ident, group });
+                #[allow(unused_imports)]
+                #vis use #callback_ident as #enum_ident;
+            }
         }
-
-        Ok(EnumGenArgs {
-            struct_attrs: attrs,
-        })
     }
 }
 
-/// Procedural macro to generate structures from enum variants. The variants can
-/// be assigned a numerical ID, which can be automatically matched by functions
-/// attributed with #[`enum_gen_match_id`].
-///
-/// The enum variants must have either named data (struct like) or no data at all.
-///
-/// # Examples
-///
-/// ```rust
-/// use enum_gen::*;
-///
-/// #[enum_gen(derive(Debug, Default), repr(C, packed))]
-/// pub enum Payload {
-///     #[attr(ID = 0x2b)]
-///     Hello { a: u8, b: u64, c: u64, d: u8 },
-///     #[attr(ID = 0x42)]
-///     Goodbye { a: u8, e: u8 },
-///     #[attr(ID = _)]
-///     Invalid,
-/// }
-/// ```
-///
-/// The `#[attr(ID = ...)]` is a mandatory attribute for every variant. The IDs must
-/// be unique, and there must be exactly one `#[attr(ID = _)]` variant which corresponds
-/// to the "default" case.
-///
-/// This will generate the following code:
-/// ```rust
-/// pub enum Payload {
-///     Hello(Hello),
-///     Goodbye(Goodbye),
-///     Invalid(Invalid),
-/// }
-/// #[derive(Debug, Default)]
-/// #[repr(C, packed)]
-///     pub struct Hello {
-///     pub a: u8,
-///     pub b: u64,
-///     pub c: u64,
-///     pub d: u8,
-/// }
-/// impl Hello {
-///     pub const ID: usize = 43usize;
-/// }
-/// #[derive(Debug, Default)]
-/// #[repr(C, packed)]
-///     pub struct Goodbye {
-///     pub a: u8,
-///     pub e: u8,
-/// }
-/// impl Goodbye {
-///     pub const ID: usize = 66usize;
-/// }
-/// #[derive(Debug, Default)]
-/// #[repr(C, packed)]
-/// pub struct Invalid {}
-/// ```
-///
-/// The IDs aren't particularly useful on their own, but can be grealy leveraged
-/// with another #[enum_gen_match_id] proc macro.  See its documentation for details.
-#[proc_macro_attribute]
-pub fn enum_gen(
-    attr: proc_macro::TokenStream,
-    input: proc_macro::TokenStream,
-) -> proc_macro::TokenStream {
-    let attr: TokenStream = attr.into();
-    let args: EnumGenArgs = attr.try_into().unwrap();
-
-    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+fn enum_gen_impl(
+    attr: TokenStream,
+    input: TokenStream,
+    input_hash: u64,
+) -> syn::Result<TokenStream> {
+    let mut errors: Option<syn::Error> = None;
+    let args: Option<EnumGenArgs> = syn::parse2(attr)
+        .map_err(|e| combine_error(&mut errors, e))
+        .ok();
+
+    // Without the enum there's nothing more to check, but still report the
+    // errors in the attribute arguments
+    let ast: syn::DeriveInput = match syn::parse2(input) {
+        Ok(ast) => ast,
+        Err(e) => {
+            combine_error(&mut errors, e);
+            return Err(errors.unwrap());
+        }
+    };
     let enum_vis = ast.vis;
     let enum_attrs = ast.attrs;
     let enum_ident = ast.ident;
+    let enum_generics = ast.generics;
 
     // Extract the enum variants
-    let variants: Vec<syn::Variant> = match ast.data {
-        syn::Data::Enum(data_enum) => data_enum.variants.into_iter().collect(),
-        _ => panic!("#[derive(ZerocopyEnum)] expects enum"),
+    let variants: Result<Vec<syn::Variant>, Span> = match ast.data {
+        syn::Data::Enum(data_enum) => Ok(data_enum.variants.into_iter().collect()),
+        syn::Data::Struct(data) => Err(data.struct_token.span),
+        syn::Data::Union(data) => Err(data.union_token.span),
+    };
+    let variants = match variants {
+        Ok(variants) => variants,
+        Err(span) => {
+            combine_error(
+                &mut errors,
+                syn::Error::new(span, "#[enum_gen] expects enum"),
+            );
+            return Err(errors.unwrap());
+        }
     };
 
     // Organize info about variants
-    let variants = variants
+    let mut auto_id = args.as_ref().and_then(|args| args.auto_id.clone());
+    let struct_affixes = args.as_ref().map_or(("", ""), |args| {
+        (args.struct_prefix.as_str(), args.struct_suffix.as_str())
+    });
+    let module = args.as_ref().and_then(|args| args.module.clone());
+    // Inside the module, the structs need to be visible to the enum's module
+    let struct_vis = match module {
+        Some(_) => vis_in_module(&enum_vis),
+        None => enum_vis.clone(),
+    };
+    let variants: Vec<EnumVariant> = variants
         .into_iter()
-        .map(|mut variant| {
-            // set visibility to each field
+        .filter_map(|mut variant| {
+            // Fields without explicit visibility inherit the enum's one
             for f in &mut variant.fields {
-                f.vis = enum_vis.clone();
+                if let syn::Visibility::Inherited = f.vis {
+                    f.vis = enum_vis.clone();
+                }
+                if module.is_some() {
+                    f.vis = vis_in_module(&f.vis);
+                }
+            }
+            let variant = EnumVariant::from_variant(
+                variant,
+                &enum_ident,
+                auto_id.as_ref(),
+                &enum_generics,
+                struct_affixes,
+            )
+            .map_err(|e| combine_error(&mut errors, e))
+            .ok()?;
+
+            // C-style, continue from the last ID of the previous variant
+            if let (Some(auto_id), EnumVariantId::Val(ranges)) = (&mut auto_id, &variant.id) {
+                *auto_id = ranges.last().unwrap().end().next();
             }
-            EnumVariant::try_from(variant)
+            Some(variant)
         })
-        .collect::<Result<Vec<EnumVariant>, _>>()
-        .unwrap();
+        .collect();
+
+    // Print some pretty messages for otherwise hard-to-debug problems
+    let mut default_variants = variants
+        .iter()
+        .filter(|v| matches!(v.id, EnumVariantId::Default));
+    if default_variants.next().is_some() {
+        for v in default_variants {
+            combine_error(
+                &mut errors,
+                syn::Error::new(
+                    v.name.span(),
+                    "Only one variant with default ID (_) can be defined.",
+                ),
+            );
+        }
+    }
+
+    let id_type: Type = args
+        .as_ref()
+        .and_then(|args| args.id_type.clone())
+        .unwrap_or_else(|| parse_quote!(usize));
+    let id_assertions = check_unique_ids(&variants, &id_type, &mut errors);
+    check_id_range(&variants, &id_type, &mut errors);
+
+    check_wrapped_types(&variants, &mut errors);
+    if args.as_ref().is_some_and(|args| args.common.is_some()) {
+        check_common_header(&variants, &mut errors);
+    }
+
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    let args = args.unwrap();
 
     // Re-create the original enum, now referencing soon-to-be-created structs
+    let extra_enum_attrs = &args.enum_attrs;
+    let enum_where_clause = &enum_generics.where_clause;
+    let enum_variants = variants
+        .iter()
+        .map(|v| v.enum_variant_tokens(module.as_ref()));
     let mut ret_stream = quote! {
         #(#enum_attrs)*
-        #enum_vis enum #enum_ident {
-            #(#variants),*
+        #(#extra_enum_attrs)*
+        #enum_vis enum #enum_ident #enum_generics #enum_where_clause {
+            #(#enum_variants),*
         }
     };
+    let mut structs_stream = TokenStream::new();
 
     // Generate struct attributes (this is the first time their syntax is checked)
     let attributes: Vec<Attribute> = args
@@ -463,130 +3062,482 @@ pub fn enum_gen(
         })
         .collect();
 
+    // The common header is either an existing type, or a struct generated
+    // next to the enum. Only the latter is known to fit in any packed struct,
+    // and only if it's packed as well
+    let header_packed = matches!(args.common, Some(CommonHeader::Fields(_)))
+        && packed_align(&attributes) == Some(1);
+    let mut packed_structs = false;
+    let header_type: Option<Type> = match args.common {
+        Some(CommonHeader::Type(ty)) => Some(ty),
+        Some(CommonHeader::Fields(mut fields)) => {
+            let header_ident = format_ident!("{}Header", enum_ident);
+            for f in &mut fields.named {
+                if let syn::Visibility::Inherited = f.vis {
+                    f.vis = enum_vis.clone();
+                }
+            }
+            let doc = format!(" Header common for all `{enum_ident}` variants");
+            ret_stream.extend(quote! {
+                #[doc = #doc]
+                #(#attributes)*
+                #enum_vis struct #header_ident #fields
+            });
+            Some(parse_quote!(#header_ident))
+        }
+        None => None,
+    };
+
+    // Existing types can't get an inherent `ID`, so their IDs are provided
+    // through a trait instead
+    let variant_id_trait_ident = variants
+        .iter()
+        .any(|v| v.use_path.is_some())
+        .then(|| format_ident!("{}VariantId", enum_ident));
+    if let Some(trait_ident) = &variant_id_trait_ident {
+        ret_stream.extend(variant_id_trait(
+            &enum_ident,
+            &enum_vis,
+            trait_ident,
+            &id_type,
+        ));
+    }
+
     // For each EnumVariant generate a struct and its impl
     for v in &variants {
-        let EnumVariant { id, name, fields } = &v;
+        let EnumVariant {
+            struct_name: name,
+            use_path,
+            fields,
+            capture,
+            cfgs,
+            struct_attrs,
+            struct_attr_overrides,
+            includes,
+            generics,
+            ..
+        } = &v;
+
+        // Variant's own attributes replace the enum-wide ones of the same name
+        let path_str = |a: &Attribute| a.path().to_token_stream().to_string();
+        let attributes = attributes
+            .iter()
+            .filter(|a| {
+                !struct_attr_overrides
+                    .iter()
+                    .any(|o| path_str(o) == path_str(a))
+            })
+            .chain(struct_attr_overrides);
+        if use_path.is_none() {
+            packed_structs |= packed_align(attributes.clone().chain(struct_attrs)).is_some();
+        }
 
-        ret_stream.extend(quote! {
-            #(#attributes)*
-            #enum_vis struct #name {
-                #(#fields,)*
+        // Captured data is stored in named fields, so a unit variant gets them too
+        let mut fields = fields.clone();
+        if let Some(capture) = capture {
+            if let Fields::Unit = fields {
+                fields = Fields::Named(parse_quote!({}));
+            }
+            if let Fields::Named(named) = &mut fields {
+                named.named.extend(capture.fields(&struct_vis, &id_type));
             }
+        }
+        // The header goes first, then the included structs
+        if !includes.is_empty() {
+            if let Fields::Unit = fields {
+                fields = Fields::Named(parse_quote!({}));
+            }
+            if let Fields::Named(named) = &mut fields {
+                for (idx, include) in includes.iter().enumerate() {
+                    let mut include = include.clone();
+                    include.vis = struct_vis.clone();
+                    named.named.insert(idx, include);
+                }
+            }
+        }
+        if let Some(header_type) = &header_type {
+            if let Fields::Unit = fields {
+                fields = Fields::Named(parse_quote!({}));
+            }
+            if let Fields::Named(named) = &mut fields {
+                let header = Field::parse_named.parse2(quote!(#struct_vis header: #header_type))?;
+                named.named.insert(0, header);
+            }
+        }
+
+        // Keep the shape of the variant - tuple and unit structs need a semicolon
+        let where_clause = &generics.where_clause;
+        let body = match &fields {
+            Fields::Named(named) => quote!(#where_clause #named),
+            Fields::Unnamed(unnamed) => quote!(#unnamed #where_clause;),
+            Fields::Unit => quote!(#where_clause;),
+        };
+        structs_stream.extend(match use_path {
+            Some(use_path) => quote! {
+                #(#cfgs)*
+                #[doc(hidden)]
+                #[allow(unused_imports)]
+                #struct_vis use #use_path as #name;
+            },
+            None => quote! {
+                #(#cfgs)*
+                #(#struct_attrs)*
+                #(#attributes)*
+                #struct_vis struct #name #generics #body
+            },
         });
 
-        if let EnumVariantId::Val(id) = id {
+        structs_stream.extend(struct_ids(
+            v,
+            &id_type,
+            &struct_vis,
+            variant_id_trait_ident.as_ref(),
+        ));
+    }
+
+    structs_stream.extend(id_assertions);
+
+    if let Some(header_type) = &header_type {
+        ret_stream.extend(header_accessors(
+            &variants,
+            &enum_ident,
+            &enum_generics,
+            header_type,
+            packed_structs && !header_packed,
+        ));
+    }
+    match &module {
+        Some(module) => {
+            let doc = format!(" Structs generated from the `{enum_ident}` variants");
             ret_stream.extend(quote! {
-                impl #name {
-                    pub const ID: usize = #id;
+                #[doc = #doc]
+                #enum_vis mod #module {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    #structs_stream
                 }
             });
         }
+        None => ret_stream.extend(structs_stream),
     }
 
-    // Lastly, save a global ref to this enum
-    if let Ok(mut cache) = CACHE.lock() {
-        let prev_val = cache.enums.insert(
-            enum_ident.to_string(),
-            EnumRef {
-                name: enum_ident.to_string(),
-                variants: variants
-                    .iter()
-                    .map(|v| EnumVariantRef {
-                        id: v.id,
-                        name: v.name.to_string(),
-                    })
-                    .collect(),
-            },
-        );
-
-        if prev_val.is_some() {
-            // TODO Lift this limitation after Span::source_file() is implemented
-            // https://github.com/rust-lang/rust/issues/54725
-            // We would put source file into the hashmap id, although ideally we would
-            // like caller's module instead.
-            drop(cache);
-            panic!("Enum name conflict! Consider using a different unique name, then create an alias to desired name");
-        } else if let Some(pending_match_fns) =
-            cache.pending_match_fns.remove(&enum_ident.to_string())
-        {
-            let enumref = cache.enums.get(&enum_ident.to_string()).unwrap();
+    let accessors = EnumAccessors::new(&variants, &id_type, module.as_ref());
+    // Opt-in, as they'd collide with e.g. the user's own `id()`
+    if args.accessors {
+        ret_stream.extend(enum_accessors(
+            &accessors,
+            &enum_ident,
+            &enum_generics,
+            &id_type,
+        ));
+    }
+    if args.traits {
+        ret_stream.extend(trait_impls(
+            &accessors,
+            &variants,
+            &enum_ident,
+            &enum_generics,
+            &id_type,
+            module.as_ref(),
+        ));
+    }
+    // Opt-in, as they'd conflict with any existing impls
+    if args.convert {
+        ret_stream.extend(conversions(
+            &variants,
+            &enum_ident,
+            &enum_generics,
+            module.as_ref(),
+        ));
+    }
 
-            for pending in pending_match_fns {
-                enum_gen_match_with_enum(enumref, &pending);
-            }
-        }
-    } else {
-        panic!("Internal chache is corrupted. Fix other problems and restart the compilation")
+    // Without a default variant, #[enum_gen_match_id] functions may fail
+    if !variants
+        .iter()
+        .any(|v| matches!(v.id, EnumVariantId::Default))
+    {
+        ret_stream.extend(unknown_id_error(&enum_ident, &enum_vis, &id_type));
     }
 
-    ret_stream.into()
+    // Lastly, export the variants for #[enum_gen_match_*] functions
+    let enumref = EnumRef {
+        id_type: args.id_type,
+        module,
+        variants: variants
+            .iter()
+            .map(|v| EnumVariantRef {
+                id: v.id.clone(),
+                name: v.name.clone(),
+                struct_name: v.struct_name.clone(),
+                cfgs: v.cfgs.clone(),
+                cfg_helper: matches!(enum_vis, syn::Visibility::Public(_)) && !v.cfgs.is_empty(),
+                capture_id: v.capture.as_ref().is_some_and(|c| c.id),
+            })
+            .collect(),
+    };
+    ret_stream.extend(callback_macro(&enumref, &enum_ident, &enum_vis, input_hash));
+
+    Ok(ret_stream)
 }
 
-/// Parsed #[enum_gen_match[_id](...)]. In case the enum definition is not available,
-/// and the impl needs to be stored in the global state.
+/// Parsed #[enum_gen_match[_id](...)] function.
 struct EnumMatchFn {
     match_by: EnumMatchType,
-    fn_str: String,
+    /// Path to the enum, as provided to #[enum_gen_match_*]
+    enum_path: Path,
+    fn_tokens: TokenStream,
 }
 
-fn enum_gen_match_with_enum(
-    enumref: &EnumRef,
-    enum_match_fn: &EnumMatchFn,
-) -> proc_macro2::TokenStream {
-    let enum_name = Ident::new(&enumref.name, Span::call_site());
-    let mut tokens: Vec<TokenTree> = proc_macro2::TokenStream::from_str(&enum_match_fn.fn_str)
-        .unwrap()
-        .into_iter()
-        .collect();
+/// Split the function into its signature and the body.
+fn split_fn_body(fn_tokens: TokenStream) -> syn::Result<(Vec<TokenTree>, Group)> {
+    let mut tokens: Vec<TokenTree> = fn_tokens.into_iter().collect();
 
     // We're expecting a function, so last Group should be the function body
-    let body = tokens
-        .pop()
-        .and_then(|t| {
-            if let TokenTree::Group(g) = t {
-                Some(g.stream())
-            } else {
-                None
+    match tokens.pop() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => Ok((tokens, g)),
+        t => Err(syn::Error::new(
+            t.map_or_else(Span::call_site, |t| t.span()),
+            "#[enum_gen_match[_id](...)] has to be used on function definition",
+        )),
+    }
+}
+
+/// Get the return type of the function, given its signature.
+fn fn_return_type(sig: &[TokenTree]) -> Option<Type> {
+    let mut seen_fn = false;
+    let mut seen_params = false;
+    let mut generics_depth = 0;
+    let mut iter = sig.iter().enumerate().peekable();
+    while let Some((idx, token)) = iter.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "fn" => seen_fn = true,
+            TokenTree::Punct(p) if p.as_char() == '-' && seen_fn => {
+                // Skip any `->` in the generics, e.g. `F: Fn() -> u8`
+                if let Some((_, TokenTree::Punct(p))) = iter.peek() {
+                    if p.as_char() == '>' {
+                        iter.next();
+                        if seen_params && generics_depth == 0 {
+                            let ret: TokenStream = sig[idx + 2..]
+                                .iter()
+                                .take_while(|t| !matches!(t, TokenTree::Ident(i) if i == "where"))
+                                .cloned()
+                                .collect();
+                            return syn::parse2(ret).ok();
+                        }
+                    }
+                }
             }
-        })
-        .expect("#[enum_gen_match[_id](...)] has to be used on function definition");
+            TokenTree::Punct(p) if p.as_char() == '<' && seen_fn => generics_depth += 1,
+            TokenTree::Punct(p) if p.as_char() == '>' && seen_fn => generics_depth -= 1,
+            TokenTree::Group(g)
+                if g.delimiter() == Delimiter::Parenthesis && seen_fn && generics_depth == 0 =>
+            {
+                seen_params = true
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Get the wrapper for the match arms, and the arm for unknown IDs, based on
+/// the return type of the function - `Option` or `Result`.
+fn unknown_id_fallback(
+    sig: &[TokenTree],
+    enum_path: &Path,
+    span: Span,
+) -> syn::Result<(TokenStream, TokenStream)> {
+    let ret_type = fn_return_type(sig);
+    let ret_ident = match &ret_type {
+        Some(Type::Path(path)) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+
+    match ret_ident.as_deref() {
+        Some("Option") => Ok((
+            quote!(::core::option::Option::Some),
+            quote!(::core::option::Option::None),
+        )),
+        Some("Result") => {
+            let enum_ident = &enum_path.segments.last().unwrap().ident;
+            let unknown_id = sibling_path(enum_path, &format_ident!("{}UnknownId", enum_ident));
+            let id = EnumMatchType::Id.ident(span);
+            Ok((
+                quote!(::core::result::Result::Ok),
+                quote!(::core::result::Result::Err(::core::convert::From::from(#unknown_id(#id)))),
+            ))
+        }
+        // Print some pretty messages for otherwise hard-to-debug problems
+        _ => {
+            let msg = "The enum has no default variant, so the function needs to return \
+                Option<_> or Result<_, _>. Alternatively, define a default variant. E.g:\n\
+                \t#[attr(ID = _)]\n\
+                Unknown";
+            Err(match ret_type {
+                Some(ret_type) => syn::Error::new_spanned(ret_type, msg),
+                None => syn::Error::new_spanned(enum_path, msg),
+            })
+        }
+    }
+}
+
+fn enum_gen_match_with_enum(
+    enumref: &EnumRef,
+    enum_match_fn: EnumMatchFn,
+) -> syn::Result<TokenStream> {
+    let (tokens, body) = split_fn_body(enum_match_fn.fn_tokens)?;
+
+    // Without a default variant, not every ID can be matched, so the function
+    // has to return an Option or a Result
+    let fallback = if matches!(enum_match_fn.match_by, EnumMatchType::Id)
+        && !enumref
+            .variants
+            .iter()
+            .any(|v| matches!(v.id, EnumVariantId::Default))
+    {
+        Some(unknown_id_fallback(
+            &tokens,
+            &enum_match_fn.enum_path,
+            body.span(),
+        )?)
+    } else {
+        None
+    };
 
     let variant_matcher = EnumVariantMatcher {
         match_by: enum_match_fn.match_by,
-        enum_name: &enum_name,
+        enum_path: &enum_match_fn.enum_path,
+        module: enumref.module.as_ref(),
+        id_type: enumref.id_type.as_ref(),
         variants: &enumref.variants,
-        case: body,
+        case: body.stream(),
+        fallback,
+        span: body.span(),
     };
 
-    let match_by = &variant_matcher.match_by;
-    quote!(
+    let match_by = variant_matcher.match_by.ident(body.span());
+    Ok(quote!(
         #(#tokens)* {
             match #match_by {
                 #variant_matcher
             }
         }
-    )
+    ))
 }
 
-fn process_match_fn(enum_name: String, enum_match_fn: EnumMatchFn) -> proc_macro::TokenStream {
-    if enum_name.is_empty() {
-        panic!("Argument is missing. Expected `#[enum_gen_match(MyEnumName)]`");
+fn process_match_fn(
+    enum_path: TokenStream,
+    match_by: EnumMatchType,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    match process_match_fn_impl(enum_path, match_by, input.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
     }
+}
 
-    let mut cache = CACHE.lock().unwrap();
-    if let Some(enumref) = cache.enums.get(&enum_name) {
-        enum_gen_match_with_enum(enumref, &enum_match_fn).into()
-    } else {
-        // We may be called before #[enum_gen], so handle it by storing
-        // this (stringified) function into cache. Unfortunately we don't
-        // know if the enum exists at all. If it doesn't, this function
-        // won't be ever instantiated, and won't generate any warning.
-        let pending_vec = cache
-            .pending_match_fns
-            .entry(enum_name)
-            .or_insert(Vec::new());
-        pending_vec.push(enum_match_fn);
-        proc_macro::TokenStream::new()
+fn process_match_fn_impl(
+    enum_path: TokenStream,
+    match_by: EnumMatchType,
+    input: TokenStream,
+) -> syn::Result<TokenStream> {
+    if enum_path.is_empty() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "Argument is missing. Expected `#[enum_gen_match(MyEnumName)]`",
+        ));
+    }
+    let enum_path: Path = syn::parse2(enum_path).map_err(|e| {
+        syn::Error::new(
+            e.span(),
+            "Invalid argument. Expected a path to the enum. E.g. `#[enum_gen_match(crate::proto::MyEnumName)]`",
+        )
+    })?;
+    split_fn_body(input.clone())?;
+
+    // The enum might not be expanded yet, or even be defined in another crate,
+    // so defer the expansion to the callback macro generated by #[enum_gen].
+    // If the enum doesn't exist, the compiler will complain about the missing
+    // macro.
+    Ok(quote! {
+        #enum_path! { #match_by (#enum_path) #input }
+    })
+}
+
+/// Expand a #[enum_gen_match_*] function. This is called by the callback macro
+/// generated by #[enum_gen], which provides the enum definition. Not meant to
+/// be used directly.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __enum_gen_match(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let parser = |input: ParseStream| {
+        let content;
+        braced!(content in input);
+        let enumref_tokens: TokenStream = content.parse()?;
+        let mut enumref: EnumRef = syn::parse2(enumref_tokens.clone())?;
+
+        // Variants enabled or not, as resolved so far by the cfg helpers
+        let mut cfgs_tokens = TokenStream::new();
+        let mut cfgs_resolved: Vec<(Ident, bool)> = Vec::new();
+        while input.peek(syn::token::Bracket) {
+            let group: Group = input.parse()?;
+            let (name, enabled) = (|input: ParseStream| {
+                let name: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                Ok((name, input.parse::<syn::LitBool>()?.value))
+            })
+            .parse2(group.stream())?;
+            cfgs_resolved.push((name, enabled));
+            cfgs_tokens.append(group);
+        }
+
+        let match_by = match input.parse::<TokenTree>()? {
+            TokenTree::Ident(ident) if ident == "id" => EnumMatchType::Id,
+            TokenTree::Ident(ident) if ident == "self" => EnumMatchType::Variant,
+            t => return Err(syn::Error::new(t.span(), "Unknown match type")),
+        };
+
+        let content;
+        parenthesized!(content in input);
+        let enum_path: Path = content.parse()?;
+
+        let fn_tokens: TokenStream = input.parse()?;
+
+        let enabled = |v: &EnumVariantRef| {
+            cfgs_resolved
+                .iter()
+                .find(|(name, _)| *name == v.name)
+                .map(|(_, enabled)| *enabled)
+        };
+        if let Some(v) = enumref
+            .variants
+            .iter()
+            .find(|v| v.cfg_helper && enabled(v).is_none())
+        {
+            let enum_ident = &enum_path.segments.last().unwrap().ident;
+            let helper = sibling_path(&enum_path, &cfg_helper_ident(enum_ident, &v.name));
+            return Ok(quote! {
+                #helper! { { #enumref_tokens } #cfgs_tokens #match_by (#enum_path) #fn_tokens }
+            });
+        }
+        enumref
+            .variants
+            .retain(|v| !v.cfg_helper || enabled(v) == Some(true));
+
+        enum_gen_match_with_enum(
+            &enumref,
+            EnumMatchFn {
+                match_by,
+                enum_path,
+                fn_tokens,
+            },
+        )
+    };
+
+    match parser.parse(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
     }
 }
 
@@ -600,6 +3551,62 @@ fn process_match_fn(enum_name: String, enum_match_fn: EnumMatchFn) -> proc_macro
 /// keep the function body minimal, potentially separating the generic logic to
 /// another helper function: `fn inner_logic_not_worth_duplicating<T: MyTrait>(v: &T)`.
 ///
+/// The function can be placed either before or after the #[`enum_gen`] enum. If the
+/// enum is never defined, the compilation fails with a "cannot find macro" error.
+///
+/// The structs are looked up next to the provided path, so with just the enum
+/// imported, the path needs to lead to it. `#[enum_gen_match_id(proto::Payload)]`
+/// would work below, but `#[enum_gen_match_id(Payload)]` fails, as `Hello` is
+/// not in scope:
+///
+/// ```compile_fail
+/// mod proto {
+///     use enum_gen::*;
+///
+///     #[enum_gen(derive(Debug, Default))]
+///     pub enum Payload {
+///         #[attr(ID = 0x2b)]
+///         Hello { a: u8 },
+///         #[attr(ID = _)]
+///         Invalid,
+///     }
+/// }
+///
+/// use enum_gen::*;
+/// use proto::Payload;
+///
+/// #[enum_gen_match_id(Payload)]
+/// pub fn default(id: usize) -> Payload {
+///     EnumVariantType(EnumStructType::default())
+/// }
+/// ```
+///
+/// If the enum has no default (`#[attr(ID = _)]`) variant, the function needs to
+/// return either `Option<_>` or `Result<_, _>`. The value of the body is then wrapped
+/// in `Some` or `Ok`, and unknown IDs return `None` or `Err`. The error is created
+/// with `From::from({Enum}UnknownId(id))`, where `{Enum}UnknownId` is generated by
+/// #[`enum_gen`] next to the enum:
+///
+/// ```rust
+/// use enum_gen::*;
+///
+/// #[enum_gen(id_type = u8, derive(Debug, Default))]
+/// pub enum Payload {
+///     #[attr(ID = 0x2b)]
+///     Hello { a: u8 },
+///     #[attr(ID = 0x42)]
+///     Goodbye { a: u8 },
+/// }
+///
+/// #[enum_gen_match_id(Payload)]
+/// pub fn parse(id: u8) -> Result<Payload, PayloadUnknownId> {
+///     EnumVariantType(EnumStructType::default())
+/// }
+///
+/// assert!(matches!(parse(0x2b), Ok(Payload::Hello(_))));
+/// assert!(matches!(parse(0x10), Err(PayloadUnknownId(0x10))));
+/// ```
+///
 /// # Examples
 /// ```rust
 /// use enum_gen::*;
@@ -648,15 +3655,7 @@ pub fn enum_gen_match_id(
     attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let attr: TokenStream = attr.into();
-    let enum_name = attr.to_string();
-
-    let enum_match_fn = EnumMatchFn {
-        match_by: EnumMatchType::Id,
-        fn_str: input.to_string(),
-    };
-
-    process_match_fn(enum_name, enum_match_fn)
+    process_match_fn(attr.into(), EnumMatchType::Id, input)
 }
 
 /// Similar to #[`enum_gen_match_id`], but matches on `self` instead.
@@ -716,13 +3715,5 @@ pub fn enum_gen_match_self(
     attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let attr: TokenStream = attr.into();
-    let enum_name = attr.to_string();
-
-    let enum_match_fn = EnumMatchFn {
-        match_by: EnumMatchType::Variant,
-        fn_str: input.to_string(),
-    };
-
-    process_match_fn(enum_name, enum_match_fn)
+    process_match_fn(attr.into(), EnumMatchType::Variant, input)
 }
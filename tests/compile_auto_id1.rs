@@ -0,0 +1,61 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(auto_id, derive(Debug, Default))]
+pub enum Command {
+    Start,
+    Stop {
+        force: bool,
+    },
+    #[attr(ID = 0x10)]
+    Reset,
+    Status,
+    #[attr(ID = _)]
+    Unknown,
+    Ping,
+}
+
+#[enum_gen_match_id(Command)]
+pub fn command(id: usize) -> Command {
+    EnumVariantType(EnumStructType::default())
+}
+
+const BASE: u8 = 0x40;
+
+#[enum_gen(auto_id = BASE, id_type = u8, derive(Debug, Default))]
+pub enum Event {
+    Connected,
+    Disconnected,
+    #[attr(ID = 2 | 5)]
+    Error,
+    Timeout,
+    #[attr(ID = _)]
+    Other,
+}
+
+#[enum_gen_match_id(Event)]
+pub fn event(id: u8) -> Event {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn auto_ids() {
+    assert_eq!(Start::ID, 0);
+    assert_eq!(Stop::ID, 1);
+    assert_eq!(Reset::ID, 0x10);
+    assert_eq!(Status::ID, 0x11);
+    assert_eq!(Ping::ID, 0x12);
+    assert!(matches!(command(1), Command::Stop(_)));
+    assert!(matches!(command(0x12), Command::Ping(_)));
+    assert!(matches!(command(2), Command::Unknown(_)));
+
+    assert_eq!(Connected::ID, 0x40);
+    assert_eq!(Disconnected::ID, 0x41);
+    assert_eq!(Timeout::ID, 6);
+    assert!(matches!(event(0x41), Event::Disconnected(_)));
+    assert!(matches!(event(6), Event::Timeout(_)));
+    assert!(matches!(event(7), Event::Other(_)));
+}
@@ -0,0 +1,62 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(id_type = u8, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = _, capture(id, bytes))]
+    Unknown,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    fn new(id: u8) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    pub fn parse(id: u8, data: &[u8]) -> Payload {
+        let mut payload = Payload::new(id);
+        if let Payload::Unknown(unknown) = &mut payload {
+            unknown.bytes = data.to_vec();
+        }
+        payload
+    }
+}
+
+#[enum_gen(derive(Debug, Default))]
+pub enum Event {
+    #[attr(ID = 1)]
+    Start,
+    #[attr(ID = _, capture)]
+    Other { reason: u8 },
+}
+
+impl Event {
+    #[enum_gen_match_id(Event)]
+    fn new(id: usize) -> Event {
+        EnumVariantType(EnumStructType::default())
+    }
+}
+
+#[test]
+fn captured_id() {
+    let Payload::Unknown(unknown) = Payload::parse(0x10, &[1, 2, 3]) else {
+        panic!("Expected unknown payload");
+    };
+    assert_eq!(unknown.id, 0x10);
+    assert_eq!(unknown.bytes, [1, 2, 3]);
+    assert!(matches!(Payload::parse(0x2b, &[]), Payload::Hello(_)));
+
+    let Event::Other(other) = Event::new(9) else {
+        panic!("Expected other event");
+    };
+    assert_eq!((other.reason, other.id), (0, 9));
+
+    let other = Other { reason: 1, id: 5 };
+    let id: usize = other.id;
+    assert_eq!((other.reason, id), (1, 5));
+}
@@ -0,0 +1,101 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(common { seq: u16, flags: u8 }, id_type = u8, derive(Debug, Default, Clone, Copy), repr(C, packed))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8, b: u64 },
+    #[attr(ID = 0x42)]
+    Ping,
+    #[attr(ID = _, capture)]
+    Unknown,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Header {
+    pub len: u32,
+}
+
+#[enum_gen(common = Header, derive(Debug, Default))]
+pub enum Event {
+    #[attr(ID = 1)]
+    Start { delay: u32 },
+    #[attr(ID = 2)]
+    Stop,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub seq: u32,
+}
+
+// The header may be misaligned, so it's accessed by value
+#[enum_gen(common = Frame, derive(Debug, Default), repr(C, packed))]
+pub enum Packet {
+    #[attr(ID = 1)]
+    Data { len: u8 },
+    #[attr(ID = 2)]
+    Ack,
+}
+
+#[enum_gen(common { seq: u32 }, derive(Debug, Default, Clone, Copy))]
+pub enum Message {
+    #[attr(ID = 1, repr(C, packed))]
+    Text { len: u8 },
+    #[attr(ID = 2)]
+    Idle,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn new(id: u8, seq: u16) -> Payload {
+        let mut payload = EnumStructType::default();
+        payload.header.seq = seq;
+        EnumVariantType(payload)
+    }
+}
+
+#[test]
+fn common_header() {
+    let hello = Hello {
+        header: PayloadHeader { seq: 1, flags: 2 },
+        a: 3,
+        b: 4,
+    };
+    let (seq, flags, a) = (hello.header.seq, hello.header.flags, hello.a);
+    assert_eq!((seq, flags, a), (1, 2, 3));
+
+    let mut payload = Payload::new(0x42, 5);
+    assert!(matches!(payload, Payload::Ping(_)));
+    let seq = payload.header().seq;
+    assert_eq!(seq, 5);
+    payload.header_mut().flags = 6;
+    let flags = payload.header().flags;
+    assert_eq!(flags, 6);
+
+    let Payload::Unknown(unknown) = Payload::new(7, 8) else {
+        panic!("Expected unknown payload");
+    };
+    let (seq, id) = (unknown.header.seq, unknown.id);
+    assert_eq!((seq, id), (8, 7));
+
+    let mut event = Event::Stop(Stop {
+        header: Header { len: 9 },
+    });
+    event.header_mut().len += 1;
+    assert_eq!(event.header(), &Header { len: 10 });
+    let start = Start::default();
+    assert_eq!((start.header.len, start.delay), (0, 0));
+
+    let mut packet = Packet::Data(Data::default());
+    packet.set_header(Frame { seq: 11 });
+    assert_eq!(packet.header(), Frame { seq: 11 });
+    assert_eq!(std::mem::offset_of!(Data, len), 4);
+
+    let mut message = Message::Text(Text::default());
+    message.set_header(MessageHeader { seq: 12 });
+    assert_eq!(message.header().seq, 12);
+}
@@ -0,0 +1,75 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+mod opcodes {
+    pub const HELLO: u16 = 0x2b;
+    pub const VENDOR_FIRST: u16 = 0x100;
+    pub const VENDOR_LAST: u16 = 0x1ff;
+}
+
+const BASE: u16 = 0x40;
+
+trait Opcode {
+    const ID: u16;
+}
+
+struct Pair<A, B>(A, B);
+
+impl Opcode for Pair<u8, u16> {
+    const ID: u16 = 0x50;
+}
+
+#[enum_gen(id_type = u16, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = opcodes::HELLO)]
+    Hello { a: u8 },
+    #[attr(ID = BASE + 3 | BASE + 4)]
+    Goodbye { a: u8 },
+    #[attr(ID = opcodes::VENDOR_FIRST..=opcodes::VENDOR_LAST)]
+    Vendor { data: u32 },
+    #[attr(ID = 0b1010 | 0o17 | 1_000 | 0x2cu16)]
+    Literals,
+    #[attr(ID = { BASE * 2 })]
+    Block,
+    #[attr(ID = BASE + 5)]
+    FooBar,
+    #[attr(ID = BASE + 6)]
+    Foobar,
+    #[attr(ID = (<Pair<u8, u16> as Opcode>::ID), derive(Debug, Default))]
+    Generic { a: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[enum_gen_match_id(Payload)]
+pub fn default(id: u16) -> Payload {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn const_ids() {
+    assert_eq!(Hello::ID, 0x2b);
+    assert_eq!(Goodbye::ID, 0x43);
+    assert_eq!(Goodbye::IDS, &[0x43..=0x43, 0x44..=0x44]);
+    assert_eq!(Vendor::IDS, &[0x100..=0x1ff]);
+    assert_eq!(Literals::IDS, &[10..=10, 15..=15, 1000..=1000, 0x2c..=0x2c]);
+    assert!(Vendor::contains_id(0x150));
+    assert!(!Vendor::contains_id(0x200));
+
+    assert!(matches!(default(opcodes::HELLO), Payload::Hello(_)));
+    assert!(matches!(default(0x44), Payload::Goodbye(_)));
+    assert!(matches!(default(0x1ff), Payload::Vendor(_)));
+    assert!(matches!(default(0o17), Payload::Literals(_)));
+    assert!(matches!(default(0x2c), Payload::Literals(_)));
+    assert_eq!(Block::ID, 0x80);
+    assert!(matches!(default(0x80), Payload::Block(_)));
+    assert_eq!((FooBar::ID, Foobar::ID), (0x45, 0x46));
+    assert!(matches!(default(0x45), Payload::FooBar(_)));
+    assert!(matches!(default(0x46), Payload::Foobar(_)));
+    assert_eq!(Generic::ID, 0x50);
+    assert!(matches!(default(0x50), Payload::Generic(_)));
+    assert!(matches!(default(0x47), Payload::Invalid(_)));
+}
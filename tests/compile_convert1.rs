@@ -0,0 +1,78 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(convert, derive(Debug, Default, PartialEq))]
+#[derive(Debug, PartialEq)]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 0x42)]
+    Ping(u32),
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[enum_gen(convert, module = event, derive(Debug, Default, PartialEq))]
+#[derive(Debug, PartialEq)]
+pub enum Event<'a, T> {
+    #[attr(ID = 1)]
+    Data { buf: &'a [u8] },
+    #[attr(ID = 2)]
+    Value(T),
+}
+
+// Without the `convert` option, hand-written impls don't conflict
+#[enum_gen(derive(Debug, Default, PartialEq))]
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    #[attr(ID = 1)]
+    Start,
+    #[attr(ID = _)]
+    Unknown,
+}
+
+impl From<Start> for Command {
+    fn from(_: Start) -> Self {
+        Command::Unknown(Unknown)
+    }
+}
+
+#[test]
+fn conversions() {
+    let payload: Payload = Hello { a: 1 }.into();
+    assert_eq!(payload, Payload::Hello(Hello { a: 1 }));
+
+    assert_eq!(Hello::try_from(payload), Ok(Hello { a: 1 }));
+    assert_eq!(
+        Hello::try_from(Payload::Invalid(Invalid)),
+        Err(Payload::Invalid(Invalid))
+    );
+
+    let mut payload = Payload::from(Ping(5));
+    let ping: &Ping = (&payload).try_into().unwrap();
+    assert_eq!(ping.0, 5);
+    assert!(<&Hello>::try_from(&payload).is_err());
+
+    let ping: &mut Ping = (&mut payload).try_into().unwrap();
+    ping.0 = 6;
+    assert_eq!(payload, Payload::Ping(Ping(6)));
+    let err: &mut Payload = <&mut Hello>::try_from(&mut payload).unwrap_err();
+    assert_eq!(err, &Payload::Ping(Ping(6)));
+
+    let buf = [1, 2];
+    let event = Event::<u8>::from(event::Data { buf: &buf });
+    assert_eq!(<&event::Data>::try_from(&event).unwrap().buf, &[1, 2]);
+    assert_eq!(
+        event::Value::try_from(event),
+        Err(Event::Data(event::Data { buf: &buf }))
+    );
+    assert_eq!(
+        event::Value::try_from(Event::<u8>::Value(event::Value(3))),
+        Ok(event::Value(3))
+    );
+
+    assert_eq!(Command::from(Start), Command::Unknown(Unknown));
+}
@@ -0,0 +1,70 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+const BASE: u8 = 0x40;
+
+#[enum_gen(accessors, id_type = u8, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 1 | 0x10..=0x1f)]
+    Vendor(u8),
+    #[attr(ID = BASE + 2)]
+    Goodbye,
+    #[attr(ID = _, capture)]
+    Unknown,
+}
+
+#[enum_gen(accessors, module = cmd, derive(Debug, Default))]
+pub enum Command {
+    #[attr(ID = 5)]
+    Start,
+    #[attr(ID = _)]
+    Other,
+}
+
+#[enum_gen_match_id(Command)]
+pub fn command(id: usize) -> Command {
+    EnumVariantType(EnumStructType::default())
+}
+
+// Without the `accessors` option, the enum's own functions don't collide
+#[enum_gen(derive(Debug, Default))]
+pub enum Event {
+    #[attr(ID = 1)]
+    Begin,
+    #[attr(ID = _)]
+    End,
+}
+
+impl Event {
+    pub fn id(&self) -> &'static str {
+        "event"
+    }
+}
+
+#[test]
+fn enum_ids() {
+    assert_eq!(Payload::PRIMARY_IDS, &[0x2b, 1, 0x42]);
+    assert_eq!(
+        Payload::VARIANT_NAMES,
+        &["Hello", "Vendor", "Goodbye", "Unknown"]
+    );
+
+    let id: u8 = Payload::Hello(Hello { a: 1 }).id();
+    assert_eq!(id, 0x2b);
+    assert_eq!(Payload::Vendor(Vendor(0)).id(), 1);
+    assert_eq!(Payload::Goodbye(Goodbye).id(), 0x42);
+    assert_eq!(Payload::Unknown(Unknown { id: 0x99 }).id(), 0x99);
+    assert_eq!(Payload::Goodbye(Goodbye).variant_name(), "Goodbye");
+
+    assert_eq!(Command::PRIMARY_IDS, &[5]);
+    assert_eq!(command(5).id(), Some(5));
+    assert_eq!(command(6).id(), None);
+    assert_eq!(command(6).variant_name(), "Other");
+
+    assert_eq!(Event::Begin(Begin).id(), "event");
+}
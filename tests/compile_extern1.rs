@@ -0,0 +1,71 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+use enum_gen_fixture::{client, event::Event, server};
+
+#[enum_gen_match_id(enum_gen_fixture::client::Payload)]
+fn client_default(id: usize) -> client::Payload {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[enum_gen_match_id(server::Payload)]
+fn server_default(id: usize) -> server::Payload {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[enum_gen_match_id(enum_gen_fixture::event::Event)]
+fn new_event(id: u8) -> Option<Event> {
+    EnumVariantType(EnumStructType::default())
+}
+
+trait TypeName {
+    fn type_name(&self) -> &'static str;
+}
+
+impl TypeName for server::Payload {
+    #[enum_gen_match_self(server::Payload)]
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<EnumStructType>()
+    }
+}
+
+impl TypeName for Event {
+    #[enum_gen_match_self(enum_gen_fixture::event::Event)]
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<EnumStructType>()
+    }
+}
+
+#[test]
+fn extern_enums() {
+    assert!(matches!(client_default(0x1), client::Payload::Login(_)));
+    assert!(matches!(client_default(0x2), client::Payload::Unknown(_)));
+    assert!(server_default(0x1).type_name().ends_with("server::Login"));
+    assert!(server_default(0x2).type_name().ends_with("server::Unknown"));
+    assert!(matches!(new_event(2), Some(Event::Stop(_))));
+    assert!(matches!(new_event(3), Some(Event::Pause(_))));
+    assert!(new_event(3).unwrap().type_name().ends_with("event::Pause"));
+    assert!(new_event(4).is_none());
+}
+
+#[test]
+#[deny(non_local_definitions)]
+fn local_pub_enum() {
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Local {
+        #[attr(ID = 0x1)]
+        First { a: u8 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+
+    #[enum_gen_match_id(Local)]
+    fn local_default(id: usize) -> Local {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    assert!(matches!(local_default(0x1), Local::First(inner) if inner.a == 0));
+    assert!(matches!(local_default(0x2), Local::Unknown(_)));
+}
@@ -0,0 +1,53 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+mod proto {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x2b)]
+        Hello {
+            /// Sequence number
+            seq: u16,
+            pub(crate) flags: u8,
+            #[allow(dead_code)]
+            pub(self) checksum: u32,
+        },
+        #[attr(ID = 0x42)]
+        Pair(u8, pub(self) u8),
+        #[attr(ID = _)]
+        Invalid,
+    }
+
+    impl Hello {
+        pub fn new(seq: u16) -> Self {
+            Hello {
+                seq,
+                flags: 1,
+                checksum: 0,
+            }
+        }
+    }
+
+    impl Pair {
+        pub fn new(a: u8) -> Self {
+            Pair(a, a)
+        }
+
+        pub fn second(&self) -> u8 {
+            self.1
+        }
+    }
+}
+
+#[test]
+fn field_visibility() {
+    let hello = proto::Hello::new(5);
+    assert_eq!(hello.seq, 5);
+    assert_eq!(hello.flags, 1);
+
+    let pair = proto::Pair::new(3);
+    assert_eq!((pair.0, pair.second()), (3, 3));
+}
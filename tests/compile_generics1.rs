@@ -0,0 +1,71 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+use std::fmt::Debug;
+
+const BASE: u8 = 0x40;
+
+#[enum_gen(id_type = u8, derive(Debug, Default))]
+pub enum Payload<'a, T: Debug + Default>
+where
+    T: Clone,
+{
+    #[attr(ID = 1)]
+    Data { buf: &'a [u8] },
+    #[attr(ID = BASE)]
+    Value { v: T },
+    #[attr(ID = BASE + 1..=BASE + 2)]
+    Both(&'a [u8], T),
+    #[attr(ID = 4)]
+    Plain { x: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl<'a, T: Debug + Default + Clone> Payload<'a, T> {
+    #[enum_gen_match_id(Payload)]
+    pub fn default(id: u8) -> Self {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Payload)]
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(inner)
+    }
+}
+
+#[enum_gen(derive(Debug))]
+pub enum Sized<const N: usize> {
+    #[attr(ID = 1)]
+    Fixed { arr: [u8; N] },
+    #[attr(ID = _)]
+    Other,
+}
+
+#[test]
+fn generic_structs() {
+    let buf = [1, 2, 3];
+    let data = Data { buf: &buf };
+    assert_eq!(data.buf, &[1, 2, 3]);
+    let value = Value {
+        v: String::from("v"),
+    };
+    assert_eq!(value.v, "v");
+    let plain: Plain = Plain { x: 1 };
+    assert_eq!(plain.x, 1);
+
+    assert_eq!(Value::<u32>::ID, 0x40);
+    assert!(Both::<u32>::contains_id(0x42));
+
+    type P<'a> = Payload<'a, u32>;
+    assert!(matches!(P::default(1), Payload::Data(_)));
+    assert!(matches!(P::default(0x41), Payload::Both(_)));
+    assert!(matches!(P::default(0x40), Payload::Value(Value { v: 0 })));
+    assert_eq!(Payload::<u64>::Value(Value { v: 0 }).size(), 8);
+    assert_eq!(Payload::<u32>::Data(data).size(), 16);
+
+    let fixed = Sized::Fixed(Fixed { arr: [0; 4] });
+    assert!(matches!(fixed, Sized::Fixed(Fixed { arr: [0, 0, 0, 0] })));
+}
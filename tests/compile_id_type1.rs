@@ -0,0 +1,51 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(id_type = u8, derive(Debug, Default), repr(C, packed))]
+pub enum Opcode {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8, b: u16 },
+    #[attr(ID = 0xff)]
+    Goodbye { a: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl Opcode {
+    #[enum_gen_match_id(Opcode)]
+    pub fn default(id: u8) -> Opcode {
+        EnumVariantType(EnumStructType::default())
+    }
+}
+
+#[enum_gen(id_type = i16, derive(Debug, Default))]
+pub enum Signed {
+    #[attr(ID = -1)]
+    Error,
+    #[attr(ID = 0x7fff)]
+    Max,
+    #[attr(ID = _)]
+    Other,
+}
+
+#[enum_gen_match_id(Signed)]
+pub fn signed_default(id: i16) -> Signed {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn typed_ids() {
+    let hello_id: u8 = Hello::ID;
+    assert_eq!(hello_id, 0x2b);
+    assert!(matches!(Opcode::default(Goodbye::ID), Opcode::Goodbye(_)));
+    assert!(matches!(Opcode::default(0), Opcode::Invalid(_)));
+
+    let error_id: i16 = Error::ID;
+    assert_eq!(error_id, -1);
+    assert!(matches!(signed_default(-1), Signed::Error(_)));
+    assert!(matches!(signed_default(Max::ID), Signed::Max(_)));
+    assert!(matches!(signed_default(1), Signed::Other(_)));
+}
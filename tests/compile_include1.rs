@@ -0,0 +1,62 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[repr(C, packed)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[repr(C, packed)]
+pub struct AuthBlock {
+    pub token: u64,
+}
+
+#[enum_gen(common { seq: u16 }, derive(Debug, Default, Clone, Copy), repr(C, packed))]
+pub enum Payload {
+    #[attr(ID = 5, include(Position))]
+    Move { speed: u16 },
+    #[attr(ID = 6, include(AuthBlock, from: Position, to: Position))]
+    Teleport,
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[test]
+fn included_structs() {
+    let m = Move {
+        header: PayloadHeader { seq: 1 },
+        position: Position { x: 2, y: 3 },
+        speed: 4,
+    };
+    assert_eq!(m.position, Position { x: 2, y: 3 });
+    assert_eq!(
+        std::mem::size_of::<Move>(),
+        std::mem::size_of::<PayloadHeader>() + std::mem::size_of::<Position>() + 2
+    );
+    assert_eq!(std::mem::offset_of!(Move, position), 2);
+    assert_eq!(std::mem::offset_of!(Move, speed), 10);
+
+    let t = Teleport {
+        auth_block: AuthBlock { token: 7 },
+        to: Position { x: 1, y: 1 },
+        ..Default::default()
+    };
+    let mut payload = Payload::Teleport(t);
+    payload.header_mut().seq = 8;
+    let Payload::Teleport(t) = payload else {
+        panic!("Expected teleport");
+    };
+    let (seq, token) = (t.header.seq, t.auth_block.token);
+    assert_eq!((seq, token), (8, 7));
+    assert_eq!(
+        (t.from, t.to),
+        (Position::default(), Position { x: 1, y: 1 })
+    );
+    assert_eq!(std::mem::offset_of!(Teleport, to), 18);
+}
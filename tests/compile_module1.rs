@@ -0,0 +1,69 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+mod proto {
+    use enum_gen::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Flags(pub u8);
+
+    const BASE: u8 = 0x40;
+
+    #[enum_gen(module = payload, id_type = u8, derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x2b)]
+        Hello { a: u8, flags: Flags },
+        #[attr(ID = BASE + 2)]
+        Goodbye(u16),
+        #[attr(ID = _, capture)]
+        Invalid,
+    }
+
+    #[enum_gen(module = cmd, auto_id, derive(Debug, Default))]
+    enum Command {
+        Start { delay: u32 },
+        Stop,
+    }
+
+    #[enum_gen_match_id(Command)]
+    fn command(id: usize) -> Option<Command> {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[test]
+    fn private_module() {
+        assert_eq!(cmd::Stop::ID, 1);
+        assert!(matches!(
+            command(0),
+            Some(Command::Start(cmd::Start { delay: 0 }))
+        ));
+    }
+}
+
+use enum_gen::*;
+use proto::{payload, Payload};
+
+#[enum_gen_match_id(Payload)]
+fn new_payload(id: u8) -> Payload {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn module() {
+    assert_eq!(payload::Hello::ID, 0x2b);
+    assert_eq!(payload::Goodbye::ID, 0x42);
+
+    let Payload::Hello(hello) = new_payload(0x2b) else {
+        panic!("Expected hello");
+    };
+    assert_eq!((hello.a, hello.flags), (0, proto::Flags(0)));
+    assert!(matches!(
+        new_payload(0x42),
+        Payload::Goodbye(payload::Goodbye(0))
+    ));
+    assert!(matches!(
+        new_payload(7),
+        Payload::Invalid(payload::Invalid { id: 7 })
+    ));
+}
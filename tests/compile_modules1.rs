@@ -0,0 +1,83 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+pub mod client {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x1)]
+        Login { user: u32 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+}
+
+pub mod server {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x1)]
+        LoginResponse { status: u8 },
+        #[attr(ID = 0x2)]
+        Kick { reason: u8 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+}
+
+// Neither the enums nor the structs are in scope here
+mod handlers {
+    use enum_gen::*;
+
+    #[enum_gen_match_id(crate::client::Payload)]
+    pub fn client_default(id: usize) -> crate::client::Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_id(super::server::Payload)]
+    pub fn server_default(id: usize) -> super::server::Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    pub trait TypeName {
+        fn type_name(&self) -> &'static str;
+    }
+
+    impl TypeName for crate::server::Payload {
+        #[enum_gen_match_self(crate::server::Payload)]
+        fn type_name(&self) -> &'static str {
+            std::any::type_name::<EnumStructType>()
+        }
+    }
+}
+
+// Only the enum is imported, so the structs are reached through the path
+mod imported {
+    use crate::server::Payload;
+    use enum_gen::*;
+
+    #[enum_gen_match_id(crate::server::Payload)]
+    pub fn server_default(id: usize) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+}
+
+#[test]
+fn path_qualified_enums() {
+    use handlers::*;
+
+    assert!(matches!(client_default(0x1), client::Payload::Login(_)));
+    assert!(matches!(
+        server_default(0x1),
+        server::Payload::LoginResponse(_)
+    ));
+    assert!(server_default(0x2).type_name().ends_with("server::Kick"));
+    assert!(server_default(0x3).type_name().ends_with("server::Unknown"));
+    assert!(matches!(
+        imported::server_default(0x2),
+        server::Payload::Kick(_)
+    ));
+}
@@ -0,0 +1,46 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(id_type = u8, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = 1 | 2 | 5)]
+    Hello { a: u8 },
+    #[attr(ID = 0x10..=0x1f)]
+    Vendor { data: u32 },
+    #[attr(ID = 3 | 0x20..=0x21)]
+    Mixed,
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[enum_gen_match_id(Payload)]
+pub fn default(id: u8) -> Payload {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn multiple_ids() {
+    assert_eq!(Hello::ID, 1);
+    assert_eq!(Hello::IDS, &[1..=1, 2..=2, 5..=5]);
+    assert!(Hello::contains_id(5));
+    assert!(!Hello::contains_id(3));
+
+    assert_eq!(Vendor::ID, 0x10);
+    assert_eq!(Vendor::IDS, &[0x10..=0x1f]);
+    assert!(Vendor::contains_id(0x1f));
+    assert!(!Vendor::contains_id(0x20));
+
+    assert_eq!(Mixed::IDS, &[3..=3, 0x20..=0x21]);
+
+    for id in [1, 2, 5] {
+        assert!(matches!(default(id), Payload::Hello(_)));
+    }
+    for id in 0x10..=0x1f {
+        assert!(matches!(default(id), Payload::Vendor(_)));
+    }
+    assert!(matches!(default(0x21), Payload::Mixed(_)));
+    assert!(matches!(default(4), Payload::Invalid(_)));
+}
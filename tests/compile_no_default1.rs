@@ -0,0 +1,65 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(id_type = u8, derive(Debug, Default))]
+#[derive(Debug)]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 0x42)]
+    Goodbye { a: u8 },
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn new(id: u8) -> Option<Payload> {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_id(Payload)]
+    pub fn try_new(id: u8) -> Result<Payload, PayloadUnknownId> {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_id(Payload)]
+    pub fn try_new_with<F: Fn() -> u8>(id: u8, f: F) -> Result<Payload, ParseError>
+    where
+        Payload: Sized,
+    {
+        EnumVariantType(EnumStructType { a: f() })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnknownId(u8),
+}
+
+impl From<PayloadUnknownId> for ParseError {
+    fn from(e: PayloadUnknownId) -> Self {
+        ParseError::UnknownId(e.0)
+    }
+}
+
+#[test]
+fn no_default_variant() {
+    assert!(matches!(Payload::new(0x2b), Some(Payload::Hello(_))));
+    assert!(Payload::new(0).is_none());
+
+    assert!(matches!(Payload::try_new(0x42), Ok(Payload::Goodbye(_))));
+    let err = Payload::try_new(7).unwrap_err();
+    assert_eq!(err, PayloadUnknownId(7));
+    assert_eq!(err.to_string(), "Unknown `Payload` ID: 7");
+
+    assert!(matches!(
+        Payload::try_new_with(0x42, || 5),
+        Ok(Payload::Goodbye(Goodbye { a: 5 }))
+    ));
+    assert_eq!(
+        Payload::try_new_with(1, || 5).unwrap_err(),
+        ParseError::UnknownId(1)
+    );
+}
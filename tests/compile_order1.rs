@@ -0,0 +1,85 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+// Match functions declared before the enum they refer to
+#[enum_gen_match_id(Payload)]
+pub fn size_of(id: usize) -> usize {
+    std::mem::size_of::<EnumStructType>()
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn default(id: usize) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Payload)]
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(inner)
+    }
+}
+
+#[enum_gen(derive(Debug, Default), repr(C, packed))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8, b: u64, c: u64, d: u8 },
+    #[attr(ID = 0x42)]
+    Goodbye { a: u8, e: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[test]
+fn match_fns_before_enum() {
+    assert_eq!(size_of(0x2b), 18);
+    assert_eq!(size_of(0x42), 2);
+    assert_eq!(size_of(0x1), 0);
+    assert_eq!(Payload::default(0x42).size(), 2);
+    assert!(matches!(Payload::default(0x1), Payload::Invalid(_)));
+}
+
+// Same enum name in a different module
+mod other {
+    use enum_gen::*;
+
+    #[enum_gen(derive(Debug, Default))]
+    pub enum Payload {
+        #[attr(ID = 0x1)]
+        Ping { seq: u32 },
+        #[attr(ID = 0x2)]
+        Pong { seq: u32 },
+        #[attr(ID = _)]
+        Unknown,
+    }
+
+    impl Payload {
+        #[enum_gen_match_id(Payload)]
+        pub fn default(id: usize) -> Payload {
+            EnumVariantType(EnumStructType::default())
+        }
+    }
+
+    pub fn seq(p: &Payload) -> u32 {
+        match p {
+            Payload::Ping(ping) => ping.seq,
+            Payload::Pong(pong) => pong.seq,
+            Payload::Unknown(_) => 0,
+        }
+    }
+}
+
+#[test]
+fn same_name_in_another_module() {
+    assert!(matches!(
+        other::Payload::default(0x1),
+        other::Payload::Ping(_)
+    ));
+    assert!(matches!(
+        other::Payload::default(0x2b),
+        other::Payload::Unknown(_)
+    ));
+    assert_eq!(other::seq(&other::Payload::default(0x2)), 0);
+}
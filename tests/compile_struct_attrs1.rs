@@ -0,0 +1,42 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(derive(Debug, Default), repr(C, packed), enum_attrs(derive(Debug)))]
+pub enum Payload {
+    #[attr(ID = 1)]
+    Hello { a: u8, b: u32 },
+    #[attr(ID = 2, derive(Debug, Default, Clone, Copy, PartialEq))]
+    Ping { seq: u16 },
+    #[attr(ID = 3, repr(C))]
+    List { len: u8, items: Vec<u32> },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn default(id: usize) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+}
+
+#[test]
+fn per_variant_attrs() {
+    // Enum-wide repr(C, packed)
+    assert_eq!(std::mem::size_of::<Hello>(), 5);
+    // Overridden repr(C)
+    assert_eq!(
+        std::mem::align_of::<List>(),
+        std::mem::align_of::<Vec<u32>>()
+    );
+
+    let ping = Ping { seq: 5 };
+    let copy = ping;
+    assert_eq!(ping, copy);
+
+    let payload = Payload::default(3);
+    assert!(format!("{payload:?}").starts_with("List("));
+}
@@ -0,0 +1,79 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(struct_suffix = "Req", derive(Debug, Default))]
+pub enum Request {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 0x42, struct = Bye)]
+    Goodbye { a: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+const BASE: u8 = 0x40;
+
+#[enum_gen(id_type = u8, struct_prefix = "Msg", struct_suffix = "Resp", derive(Debug, Default))]
+pub enum Response {
+    #[attr(ID = 0x2b)]
+    Hello { b: u16 },
+    #[attr(ID = BASE + 2)]
+    Goodbye(u8),
+}
+
+// Raw identifiers, for the module as well
+#[enum_gen(module = r#type, derive(Debug, Default))]
+pub enum Raw {
+    #[attr(ID = 0x1, struct = r#Foo)]
+    Foo { a: u8 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl Request {
+    #[enum_gen_match_id(Request)]
+    pub fn new(id: usize) -> Request {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Request)]
+    pub fn name(&self) -> &'static str {
+        std::any::type_name::<EnumStructType>()
+    }
+}
+
+#[enum_gen_match_id(Response)]
+pub fn response(id: u8) -> Option<Response> {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[enum_gen_match_id(Raw)]
+pub fn raw(id: usize) -> Raw {
+    EnumVariantType(EnumStructType::default())
+}
+
+#[test]
+fn struct_names() {
+    assert_eq!(HelloReq::ID, 0x2b);
+    assert_eq!(Bye::ID, 0x42);
+    assert_eq!(MsgHelloResp::ID, 0x2b);
+    assert_eq!(MsgGoodbyeResp::ID, 0x42);
+
+    assert!(matches!(
+        Request::new(0x2b),
+        Request::Hello(HelloReq { a: 0 })
+    ));
+    assert!(matches!(Request::new(0x42), Request::Goodbye(Bye { a: 0 })));
+    assert!(Request::new(1).name().ends_with("InvalidReq"));
+    assert!(matches!(
+        response(0x42),
+        Some(Response::Goodbye(MsgGoodbyeResp(0)))
+    ));
+    assert!(response(1).is_none());
+
+    assert!(matches!(raw(0x1), Raw::Foo(r#type::Foo { a: 0 })));
+    assert!(matches!(raw(0x2), Raw::Invalid(_)));
+}
@@ -0,0 +1,52 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+use enum_gen_traits::{EnumGen, EnumGenVariant};
+
+#[enum_gen(traits, id_type = u8, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 1 | 2)]
+    Ping(u32),
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[enum_gen(traits, module = event, derive(Debug, Default))]
+pub enum Event<T> {
+    #[attr(ID = 1)]
+    Value(T),
+    #[attr(ID = 2)]
+    Stop,
+    #[attr(ID = _, capture)]
+    Other,
+}
+
+fn wrap<V: EnumGenVariant>(v: V) -> (V::Id, V::Enum) {
+    (V::ID, V::Enum::from_variant(v))
+}
+
+fn describe<E: EnumGen>(e: &E) -> (Option<E::Id>, &'static str) {
+    (e.id(), e.variant_name())
+}
+
+#[test]
+fn traits() {
+    let (id, payload) = wrap(Hello { a: 1 });
+    assert_eq!(id, 0x2b);
+    assert_eq!(describe(&payload), (Some(0x2b), "Hello"));
+    assert_eq!(describe(&Payload::Invalid(Invalid)), (None, "Invalid"));
+    assert_eq!(<Ping as EnumGenVariant>::IDS, &[1..=1, 2..=2]);
+    assert_eq!(<Ping as EnumGenVariant>::NAME, "Ping");
+    assert_eq!(<Payload as EnumGen>::PRIMARY_IDS, &[0x2b, 1]);
+
+    let (id, event) = wrap(event::Value(5u8));
+    assert_eq!(id, 1);
+    assert_eq!(describe(&event), (Some(1), "Value"));
+    let other = Event::<u8>::Other(event::Other { id: 9 });
+    assert_eq!(describe(&other), (Some(9), "Other"));
+    assert_eq!(<Event<u8> as EnumGen>::VARIANT_NAMES.len(), 3);
+}
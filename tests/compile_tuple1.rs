@@ -0,0 +1,46 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(derive(Debug, Default, PartialEq))]
+pub enum Payload {
+    #[attr(ID = 1)]
+    Ping(u32),
+    #[attr(ID = 2)]
+    Pair(u8, u16),
+    #[attr(ID = 3)]
+    Hello { a: u8 },
+    #[attr(ID = 4)]
+    Ack,
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn default(id: usize) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Payload)]
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(inner)
+    }
+}
+
+#[test]
+fn struct_shapes() {
+    let ping = Ping(5);
+    assert_eq!(ping.0, 5);
+    let pair = Pair(1, 2);
+    assert_eq!((pair.0, pair.1), (1, 2));
+    assert_eq!(std::mem::size_of::<Ack>(), 0);
+    assert_eq!(std::mem::size_of::<Invalid>(), 0);
+
+    assert!(matches!(Payload::default(1), Payload::Ping(Ping(0))));
+    assert!(matches!(Payload::default(4), Payload::Ack(Ack)));
+    assert_eq!(Payload::Ping(ping).size(), 4);
+    assert_eq!(Payload::Ack(Ack).size(), 0);
+}
@@ -0,0 +1,81 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+mod other {
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Status {
+        pub code: u8,
+    }
+
+    impl Status {
+        pub fn is_ok(&self) -> bool {
+            self.code == 0
+        }
+    }
+}
+
+const BASE: u8 = 0x40;
+
+#[enum_gen(id_type = u8, derive(Debug, Default))]
+pub enum Payload {
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[attr(ID = 7 | 9, use = other::Status)]
+    Status,
+    #[attr(ID = BASE + 1, use = std::time::Duration)]
+    Delay,
+    #[attr(ID = _)]
+    Invalid,
+}
+
+#[enum_gen(module = event, derive(Debug, Default))]
+pub enum Event {
+    #[attr(ID = 1, use = other::Status)]
+    Status,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn new(id: u8) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Payload)]
+    pub fn name(&self) -> &'static str {
+        std::any::type_name::<EnumStructType>()
+    }
+}
+
+#[enum_gen_match_id(Event)]
+pub fn event(id: usize) -> Option<Event> {
+    EnumVariantType(EnumStructType::default())
+}
+
+fn id_of<T: PayloadVariantId>(_: &T) -> u8 {
+    T::ID
+}
+
+#[test]
+fn existing_types() {
+    assert_eq!(<other::Status as PayloadVariantId>::ID, 7);
+    assert!(<other::Status as PayloadVariantId>::contains_id(9));
+    assert_eq!(<std::time::Duration as PayloadVariantId>::ID, 0x41);
+    assert_eq!(id_of(&other::Status { code: 1 }), 7);
+    assert_eq!(Hello::ID, 0x2b);
+
+    let Payload::Status(status) = Payload::new(9) else {
+        panic!("Expected status");
+    };
+    assert!(status.is_ok());
+    assert!(matches!(Payload::new(0x41), Payload::Delay(d) if d.is_zero()));
+    assert!(Payload::new(7).name().ends_with("other::Status"));
+
+    assert!(matches!(
+        event(1),
+        Some(Event::Status(other::Status { code: 0 }))
+    ));
+    assert_eq!(<other::Status as EventVariantId>::ID, 1);
+}
@@ -0,0 +1,51 @@
+/* SPDX-License-Identifier: MIT
+ * Copyright(c) 2023 Darek Stojaczyk
+ */
+
+use enum_gen::*;
+
+#[enum_gen(derive(Debug, Default))]
+pub enum Payload {
+    /// Greeting sent by the client
+    #[attr(ID = 0x2b)]
+    Hello { a: u8 },
+    #[cfg(not(test))]
+    #[attr(ID = 0x42)]
+    Disabled { a: u8 },
+    /// Same ID as the disabled variant, which is fine as long as they're never
+    /// enabled together
+    #[cfg(test)]
+    #[attr(ID = 0x42)]
+    Goodbye { a: u8 },
+    #[deprecated]
+    #[attr(ID = 0x43)]
+    Old,
+    #[struct_attr(derive(Clone, PartialEq))]
+    #[variant_attr(doc = "Only the variant has this doc")]
+    #[attr(ID = 0x44)]
+    Ack { seq: u16 },
+    #[attr(ID = _)]
+    Invalid,
+}
+
+impl Payload {
+    #[enum_gen_match_id(Payload)]
+    pub fn default(id: usize) -> Payload {
+        EnumVariantType(EnumStructType::default())
+    }
+
+    #[enum_gen_match_self(Payload)]
+    pub fn name(&self) -> &'static str {
+        std::any::type_name::<EnumStructType>()
+    }
+}
+
+#[test]
+fn variant_attrs() {
+    assert!(matches!(Payload::default(0x2b), Payload::Hello(_)));
+    assert!(matches!(Payload::default(0x42), Payload::Goodbye(_)));
+    assert!(Payload::default(0x43).name().ends_with("Old"));
+
+    let ack = Ack { seq: 1 };
+    assert_eq!(ack.clone(), ack);
+}